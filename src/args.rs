@@ -0,0 +1,114 @@
+use std::path::PathBuf;
+
+use crate::{
+    demo::{DemoArgs, DemoMode},
+    recording::ReplayArgs,
+};
+
+const USAGE: &str = "\
+usage: ststat [--demo[=FIXTURE] | --mock-lhm[=FIXTURE]] [--port=PORT] [--static]
+       ststat --replay=FILE [--speed=SPEED]
+  FIXTURE is intel, amd, laptop, multi_gpu or the path to a data.json
+  lm_sensors and lm_sensors_laptop, or the path to a sensors -j output serve lm-sensors chips
+  --port defaults to a free port for --demo and 8085 for --mock-lhm
+  --static serves the fixture as is instead of animating the values
+  --replay plays back a .rec.gz recorded from the settings, SPEED 2 is twice as fast";
+
+#[derive(Debug, Default)]
+pub struct Args {
+    pub demo: Option<DemoArgs>,
+    pub replay: Option<ReplayArgs>,
+}
+
+impl Args {
+    pub fn parse(args: impl Iterator<Item = String>) -> Result<Args, String> {
+        let mut demo_mode = None;
+        let mut fixture = "intel".to_string();
+        let mut animate = true;
+        let mut port = None;
+        let mut replay = None;
+        let mut speed = None;
+
+        for arg in args {
+            let (flag, value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag, Some(value)),
+                None => (arg.as_str(), None),
+            };
+            match (flag, value) {
+                ("--demo", v) | ("--mock-lhm", v) => {
+                    demo_mode = Some(if flag == "--demo" {
+                        DemoMode::Demo
+                    } else {
+                        DemoMode::MockLhm
+                    });
+                    if let Some(v) = v {
+                        fixture = v.to_string();
+                    }
+                }
+                ("--static", None) => animate = false,
+                ("--port", Some(v)) => {
+                    port = Some(v.parse::<u16>().map_err(|_| usage("invalid port"))?)
+                }
+                ("--replay", Some(v)) => replay = Some(PathBuf::from(v)),
+                ("--speed", Some(v)) => {
+                    speed = Some(
+                        v.parse::<f64>()
+                            .ok()
+                            .filter(|s| *s > 0.0)
+                            .ok_or_else(|| usage("invalid speed"))?,
+                    )
+                }
+                _ => return Err(usage(&format!("unknown argument {arg}"))),
+            }
+        }
+
+        if demo_mode.is_some() && replay.is_some() {
+            return Err(usage(
+                "--replay can't be combined with --demo or --mock-lhm",
+            ));
+        }
+        if speed.is_some() && replay.is_none() {
+            return Err(usage("--speed only applies to --replay"));
+        }
+        let demo = demo_mode
+            .map(|mode| DemoArgs::new(mode, &fixture, animate, port))
+            .transpose()
+            .map_err(|e| usage(&e))?;
+        Ok(Args {
+            demo,
+            replay: replay.map(|path| ReplayArgs {
+                path,
+                speed: speed.unwrap_or(1.0),
+            }),
+        })
+    }
+}
+
+fn usage(error: &str) -> String {
+    format!("{error}\n{USAGE}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        Args::parse(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn speed_needs_replay() {
+        let error = parse(&["--speed=2"]).unwrap_err();
+        assert!(error.starts_with("--speed only applies to --replay"));
+        let error = parse(&["--demo", "--speed=2"]).unwrap_err();
+        assert!(error.starts_with("--speed only applies to --replay"));
+
+        let replay = parse(&["--replay=a.rec.gz", "--speed=2"])
+            .unwrap()
+            .replay
+            .unwrap();
+        assert_eq!(replay.speed, 2.0);
+        let replay = parse(&["--replay=a.rec.gz"]).unwrap().replay.unwrap();
+        assert_eq!(replay.speed, 1.0);
+    }
+}
@@ -0,0 +1,190 @@
+//! A stand-in for the LibreHardwareMonitor web server that serves recorded data.json files, to
+//! run the sidebar on machines without LHM.
+
+use std::time::Instant;
+
+use serde_json::Value;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    runtime::Runtime,
+};
+
+use crate::{lm_sensors::sensors_tree, settings::LhmSettings};
+
+const FIXTURES: [(&str, &str); 4] = [
+    ("intel", include_str!("../fixtures/lhm/intel.json")),
+    ("amd", include_str!("../fixtures/lhm/amd.json")),
+    ("laptop", include_str!("../fixtures/lhm/laptop.json")),
+    ("multi_gpu", include_str!("../fixtures/lhm/multi_gpu.json")),
+];
+
+/// Captured `sensors -j` output, served as the tree it converts to.
+const LM_SENSORS_FIXTURES: [(&str, &str); 2] = [
+    (
+        "lm_sensors",
+        include_str!("../fixtures/lm_sensors/desktop_intel.json"),
+    ),
+    (
+        "lm_sensors_laptop",
+        include_str!("../fixtures/lm_sensors/laptop_amd.json"),
+    ),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DemoMode {
+    /// Run the sidebar against the stand-in server.
+    Demo,
+    /// Only run the server, in place of LHM.
+    MockLhm,
+}
+
+#[derive(Debug, Clone)]
+pub struct DemoArgs {
+    pub mode: DemoMode,
+    data: Value,
+    animate: bool,
+    port: u16,
+}
+
+impl DemoArgs {
+    /// `fixture` is the name of a bundled recording or the path to a data.json or a `sensors -j`
+    /// output.
+    pub fn new(
+        mode: DemoMode,
+        fixture: &str,
+        animate: bool,
+        port: Option<u16>,
+    ) -> Result<Self, String> {
+        let json = match FIXTURES
+            .iter()
+            .chain(&LM_SENSORS_FIXTURES)
+            .find(|(name, _)| *name == fixture)
+        {
+            Some((_, json)) => json.to_string(),
+            None => std::fs::read_to_string(fixture)
+                .map_err(|e| format!("can't read fixture {fixture}: {e}"))?,
+        };
+        let mut data: Value = serde_json::from_str(&json).map_err(|e| format!("{fixture}: {e}"))?;
+        // LHM's tree always starts with the "Sensor" node, lm-sensors' output with the chips
+        if data.get("Children").is_none() {
+            let tree = sensors_tree(&json).map_err(|e| format!("{fixture}: {e}"))?;
+            data = serde_json::to_value(tree).map_err(|e| format!("{fixture}: {e}"))?;
+        }
+
+        Ok(DemoArgs {
+            mode,
+            data,
+            animate,
+            port: port.unwrap_or(if mode == DemoMode::Demo { 0 } else { 8085 }),
+        })
+    }
+}
+
+/// Starts the server in the background, returns where to find it.
+pub fn spawn_demo_server(rt: &Runtime, args: &DemoArgs) -> std::io::Result<LhmSettings> {
+    let listener = rt.block_on(TcpListener::bind(("127.0.0.1", args.port)))?;
+    let port = listener.local_addr()?.port();
+    println!("Demo data on http://127.0.0.1:{port}/data.json");
+    rt.spawn(serve(listener, args.data.clone(), args.animate));
+    Ok(LhmSettings {
+        host: "127.0.0.1".to_string(),
+        port,
+        ..Default::default()
+    })
+}
+
+pub fn run_mock_lhm(args: DemoArgs) -> std::io::Result<()> {
+    let rt = Runtime::new()?;
+    rt.block_on(async {
+        let listener = TcpListener::bind(("127.0.0.1", args.port)).await?;
+        println!("Serving on http://127.0.0.1:{}/data.json", args.port);
+        serve(listener, args.data, args.animate).await;
+        Ok::<(), std::io::Error>(())
+    })
+}
+
+async fn serve(listener: TcpListener, data: Value, animate: bool) {
+    let started = Instant::now();
+    loop {
+        let Ok((stream, _)) = listener.accept().await else {
+            continue;
+        };
+        let mut data = data.clone();
+        if animate {
+            animate_values(&mut data, started.elapsed().as_secs_f64());
+        }
+        tokio::spawn(respond(stream, data));
+    }
+}
+
+/// Every request gets the whole response and the connection is closed, like a very small
+/// HTTP/1.1 server.
+async fn respond(mut stream: TcpStream, data: Value) {
+    let mut buf = [0u8; 4096];
+    let Ok(len) = stream.read(&mut buf).await else {
+        return;
+    };
+    let request = String::from_utf8_lossy(&buf[..len]);
+    let path = request.split_whitespace().nth(1).unwrap_or_default();
+
+    let (status, body) = if path == "/data.json" {
+        ("200 OK", data.to_string())
+    } else {
+        ("404 Not Found", String::new())
+    };
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    let _ = stream.write_all(response.as_bytes()).await;
+}
+
+/// Lets loads, temperatures, clocks, power and fans drift around their recorded value. Every
+/// sensor moves on its own phase so the graphs don't all look the same.
+fn animate_values(node: &mut Value, t: f64) {
+    let Some(obj) = node.as_object_mut() else {
+        return;
+    };
+    let id = obj.get("id").and_then(Value::as_f64).unwrap_or_default();
+    let factor = 1.0 + 0.2 * (t / 7.0 + id).sin() + 0.05 * (t * 1.3 + id * 3.0).sin();
+
+    let wobbled = obj
+        .get("Value")
+        .and_then(Value::as_str)
+        .and_then(|v| wobble(v, factor));
+    if let Some(value) = wobbled {
+        obj.insert("Value".to_string(), Value::String(value));
+        if let Some(raw) = obj.get("RawValue").and_then(Value::as_f64) {
+            obj.insert("RawValue".to_string(), (raw * factor).into());
+        }
+    }
+
+    if let Some(Value::Array(children)) = obj.get_mut("Children") {
+        for c in children {
+            animate_values(c, t);
+        }
+    }
+}
+
+/// `"45.0 °C"` scaled by `factor`, keeping LHM's formatting.
+fn wobble(value: &str, factor: f64) -> Option<String> {
+    let (number, unit) = value.split_once(' ')?;
+    if !["%", "°C", "W", "MHz", "RPM"].contains(&unit) {
+        return None;
+    }
+    let decimal_comma = number.contains(',');
+    let decimals = number
+        .split_once(['.', ','])
+        .map(|(_, d)| d.len())
+        .unwrap_or(0);
+    let mut v = number.replace(',', ".").parse::<f64>().ok()? * factor;
+    if unit == "%" {
+        v = v.clamp(0.0, 100.0);
+    }
+    let mut number = format!("{v:.decimals$}");
+    if decimal_comma {
+        number = number.replace('.', ",");
+    }
+    Some(format!("{number} {unit}"))
+}
@@ -0,0 +1,218 @@
+//! Just enough DNS to time a lookup: one A query, and the response code of the answer.
+
+use std::{
+    io::{Error, ErrorKind},
+    net::SocketAddr,
+    time::Duration,
+};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpStream, UdpSocket},
+    time::timeout,
+};
+
+const HEADER_LEN: usize = 12;
+
+/// RCODE of a response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DnsAnswer {
+    NoError,
+    NxDomain,
+    /// Any other code, e.g. 2 for SERVFAIL or 5 for REFUSED
+    Error(u8),
+}
+
+/// Sends one A query for `name` and waits for the matching response.
+pub async fn lookup(
+    resolver: SocketAddr,
+    name: &str,
+    tcp: bool,
+    time_limit: Duration,
+) -> std::io::Result<DnsAnswer> {
+    // a guessable id lets anyone on the path answer in place of the resolver
+    let id = rand::random::<u16>();
+    let query = encode_query(id, name)?;
+    let exchange = async {
+        if tcp {
+            exchange_tcp(resolver, &query).await
+        } else {
+            exchange_udp(resolver, &query, id).await
+        }
+    };
+    let response = timeout(time_limit, exchange)
+        .await
+        .map_err(|_| Error::from(ErrorKind::TimedOut))??;
+    parse_response(id, &response)
+}
+
+async fn exchange_udp(resolver: SocketAddr, query: &[u8], id: u16) -> std::io::Result<Vec<u8>> {
+    let local: SocketAddr = if resolver.is_ipv4() {
+        ([0, 0, 0, 0], 0).into()
+    } else {
+        ([0u16; 8], 0).into()
+    };
+    let socket = UdpSocket::bind(local).await?;
+    socket.connect(resolver).await?;
+    socket.send(query).await?;
+    let mut buf = [0u8; 512];
+    loop {
+        let len = socket.recv(&mut buf).await?;
+        // a late answer to an earlier query isn't this one
+        if len >= 2 && u16::from_be_bytes([buf[0], buf[1]]) == id {
+            return Ok(buf[..len].to_vec());
+        }
+    }
+}
+
+/// Over TCP every message is prefixed with its length.
+async fn exchange_tcp(resolver: SocketAddr, query: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut stream = TcpStream::connect(resolver).await?;
+    let mut message = (query.len() as u16).to_be_bytes().to_vec();
+    message.extend_from_slice(query);
+    stream.write_all(&message).await?;
+    let len = stream.read_u16().await?;
+    let mut response = vec![0u8; len as usize];
+    stream.read_exact(&mut response).await?;
+    Ok(response)
+}
+
+/// A recursive query for the A record of `name`.
+pub fn encode_query(id: u16, name: &str) -> std::io::Result<Vec<u8>> {
+    let mut query = Vec::with_capacity(HEADER_LEN + name.len() + 6);
+    query.extend_from_slice(&id.to_be_bytes());
+    // RD set, one question
+    query.extend_from_slice(&[0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]);
+    for label in name.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("invalid name {name}"),
+            ));
+        }
+        query.push(label.len() as u8);
+        query.extend_from_slice(label.as_bytes());
+    }
+    // root, type A, class IN
+    query.extend_from_slice(&[0, 0, 1, 0, 1]);
+    Ok(query)
+}
+
+pub fn parse_response(id: u16, response: &[u8]) -> std::io::Result<DnsAnswer> {
+    // the QR bit tells a response from a query
+    if response.len() < HEADER_LEN
+        || u16::from_be_bytes([response[0], response[1]]) != id
+        || response[2] & 0x80 == 0
+    {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "not a response to the query",
+        ));
+    }
+    Ok(match response[3] & 0x0f {
+        0 => DnsAnswer::NoError,
+        3 => DnsAnswer::NxDomain,
+        code => DnsAnswer::Error(code),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tokio::runtime::Runtime;
+
+    /// Header of a response to `id` with `rcode`, the question doesn't matter here.
+    fn response(id: u16, flags: u8, rcode: u8) -> Vec<u8> {
+        let mut response = id.to_be_bytes().to_vec();
+        response.extend_from_slice(&[flags, 0x80 | rcode, 0, 1, 0, 0, 0, 0, 0, 0]);
+        response
+    }
+
+    #[test]
+    fn encodes_an_a_query() {
+        let query = encode_query(0x1234, "www.example.com.").unwrap();
+        let mut expected = vec![0x12, 0x34, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0];
+        expected.extend_from_slice(b"\x03www\x07example\x03com\x00");
+        expected.extend_from_slice(&[0, 1, 0, 1]);
+        assert_eq!(query, expected);
+
+        assert!(encode_query(1, "a..b").is_err());
+        assert!(encode_query(1, &format!("{}.com", "a".repeat(64))).is_err());
+    }
+
+    #[test]
+    fn rejects_what_doesnt_answer_the_query() {
+        assert!(parse_response(7, &response(8, 0x81, 0)).is_err());
+        // QR not set, a query
+        assert!(parse_response(7, &response(7, 0x01, 0)).is_err());
+        assert!(parse_response(7, &[0, 7, 0x81]).is_err());
+    }
+
+    #[test]
+    fn maps_response_codes() {
+        assert_eq!(
+            parse_response(7, &response(7, 0x81, 0)).unwrap(),
+            DnsAnswer::NoError
+        );
+        assert_eq!(
+            parse_response(7, &response(7, 0x81, 3)).unwrap(),
+            DnsAnswer::NxDomain
+        );
+        assert_eq!(
+            parse_response(7, &response(7, 0x81, 2)).unwrap(),
+            DnsAnswer::Error(2)
+        );
+    }
+
+    #[test]
+    fn looks_up_at_a_stub_resolver() {
+        Runtime::new().unwrap().block_on(async {
+            let resolver = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+            let address = resolver.local_addr().unwrap();
+            tokio::spawn(async move {
+                let mut buf = [0u8; 512];
+                for rcode in [0, 3] {
+                    let (len, from) = resolver.recv_from(&mut buf).await.unwrap();
+                    let query = &buf[..len];
+                    let id = u16::from_be_bytes([query[0], query[1]]);
+                    // a stale answer first, it has to be skipped
+                    let stale = response(id.wrapping_add(1), 0x81, 2);
+                    resolver.send_to(&stale, from).await.unwrap();
+                    let mut answer = response(id, 0x81, rcode);
+                    answer.extend_from_slice(&query[HEADER_LEN..]);
+                    resolver.send_to(&answer, from).await.unwrap();
+                }
+            });
+
+            let limit = Duration::from_secs(2);
+            assert_eq!(
+                lookup(address, "example.com", false, limit).await.unwrap(),
+                DnsAnswer::NoError
+            );
+            assert_eq!(
+                lookup(address, "nope.example.com", false, limit)
+                    .await
+                    .unwrap(),
+                DnsAnswer::NxDomain
+            );
+        });
+    }
+
+    #[test]
+    fn times_out_without_an_answer() {
+        Runtime::new().unwrap().block_on(async {
+            // bound, but never answers
+            let silent = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+            let error = lookup(
+                silent.local_addr().unwrap(),
+                "example.com",
+                false,
+                Duration::from_millis(100),
+            )
+            .await
+            .unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::TimedOut);
+        });
+    }
+}
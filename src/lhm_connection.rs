@@ -0,0 +1,254 @@
+use std::{fmt::Display, sync::Arc};
+
+use chrono::{DateTime, Duration, Local};
+use parking_lot::Mutex;
+use tokio::{process::Command, time::sleep};
+
+use crate::{
+    lm_sensors::sensors_tree,
+    ohw::OHWNode,
+    settings::{LhmSettings, MySettings},
+};
+
+/// How long the last tree is kept after requests start failing, so a single slow response
+/// doesn't blank every value.
+const STALE_AFTER_SECS: i64 = 5;
+const MAX_BACKOFF_SECS: i64 = 30;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum LhmState {
+    /// No request finished yet
+    #[default]
+    Connecting,
+    Connected,
+    /// The last request failed, the previous data is still shown.
+    Stale(String),
+    /// Nothing answers, LHM or its web server isn't running.
+    Unreachable(String),
+    Timeout,
+    Http(u16),
+    Parse(String),
+    /// `sensors` couldn't be run or failed.
+    Command(String),
+}
+
+impl Display for LhmState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LhmState::Connecting => write!(f, "connecting"),
+            LhmState::Connected => write!(f, "connected"),
+            LhmState::Stale(e) => write!(f, "no new data: {e}"),
+            LhmState::Unreachable(e) => write!(f, "not reachable ({e}), is the web server on?"),
+            LhmState::Timeout => write!(f, "timed out"),
+            LhmState::Http(401) => write!(f, "HTTP 401, check user and password"),
+            LhmState::Http(code) => write!(f, "HTTP {code}"),
+            LhmState::Parse(e) => write!(f, "invalid data.json: {e}"),
+            LhmState::Command(e) => write!(f, "`sensors -j` failed: {e}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LhmStatus {
+    pub state: LhmState,
+    pub last_success: Option<DateTime<Local>>,
+    /// Failed requests in a row
+    pub failures: u32,
+    pub next_attempt: Option<DateTime<Local>>,
+}
+
+impl LhmStatus {
+    /// One line for the sidebar, `None` while everything is fine.
+    pub fn summary(&self) -> Option<String> {
+        if self.state == LhmState::Connected {
+            return None;
+        }
+        let mut s = format!("LHM {}", self.state);
+        if let Some(next) = self.next_attempt {
+            let secs = (next - Local::now()).num_seconds();
+            if secs > 1 {
+                s += &format!(", retry in {secs}s");
+            }
+        }
+        Some(s)
+    }
+}
+
+/// Fetches data.json once a second. Failed requests are retried with exponential backoff.
+/// `lhm_override` replaces the configured server, without touching the settings.
+pub async fn ohw_thread(
+    thread_ohw: Arc<Mutex<Option<OHWNode>>>,
+    status: Arc<Mutex<LhmStatus>>,
+    settings: Arc<Mutex<MySettings>>,
+    lhm_override: Option<LhmSettings>,
+) -> ! {
+    let mut current: Option<(LhmSettings, reqwest::Client)> = None;
+    loop {
+        // pick up changed settings without a restart, and don't make the user wait for the
+        // backoff to see if they worked
+        let lhm = lhm_override
+            .clone()
+            .unwrap_or_else(|| settings.lock().current_settings.lhm.clone());
+        if current.as_ref().map(|(s, _)| s) != Some(&lhm) {
+            let client = reqwest::Client::builder()
+                .timeout(std::time::Duration::from_millis(lhm.timeout_ms))
+                .build()
+                .unwrap_or_default();
+            current = Some((lhm, client));
+            let mut s = status.lock();
+            s.failures = 0;
+            s.next_attempt = None;
+        }
+
+        let next_attempt = status.lock().next_attempt;
+        if next_attempt.map(|n| n <= Local::now()).unwrap_or(true) {
+            let (lhm, client) = current.as_ref().unwrap();
+            let result = fetch(lhm, client).await;
+            update_status(&thread_ohw, &status, result);
+        }
+
+        sleep(
+            Duration::milliseconds(
+                (1000
+                    - Local::now()
+                        .naive_local()
+                        .and_utc()
+                        .timestamp_subsec_millis() as i64)
+                    .clamp(520, 999),
+            )
+            .to_std()
+            .unwrap(),
+        )
+        .await;
+    }
+}
+
+async fn fetch(lhm: &LhmSettings, client: &reqwest::Client) -> Result<OHWNode, LhmState> {
+    if lhm.lm_sensors {
+        return read_lm_sensors(lhm.timeout_ms).await;
+    }
+    let mut request = client.get(lhm.url());
+    if !lhm.username.is_empty() {
+        request = request.basic_auth(&lhm.username, Some(&lhm.password).filter(|p| !p.is_empty()));
+    }
+
+    let response = request.send().await.map_err(request_error)?;
+    if !response.status().is_success() {
+        return Err(LhmState::Http(response.status().as_u16()));
+    }
+    let body = response.text().await.map_err(request_error)?;
+    serde_json::from_str(&body).map_err(|e| LhmState::Parse(e.to_string()))
+}
+
+async fn read_lm_sensors(timeout_ms: u64) -> Result<OHWNode, LhmState> {
+    let output = tokio::time::timeout(
+        std::time::Duration::from_millis(timeout_ms),
+        Command::new("sensors")
+            .arg("-j")
+            .kill_on_drop(true)
+            .output(),
+    )
+    .await
+    .map_err(|_| LhmState::Timeout)?
+    .map_err(|e| LhmState::Command(e.to_string()))?;
+    // a single unreadable subfeature fails the exit code, but the rest is still there
+    if !output.status.success() && output.stdout.is_empty() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(LhmState::Command(stderr.trim().to_string()));
+    }
+    sensors_tree(&String::from_utf8_lossy(&output.stdout))
+        .map_err(|e| LhmState::Command(format!("unexpected output: {e}")))
+}
+
+fn request_error(e: reqwest::Error) -> LhmState {
+    if e.is_timeout() {
+        return LhmState::Timeout;
+    }
+    // reqwest only says "error sending request", the interesting part is the innermost cause
+    let mut cause: &dyn std::error::Error = &e;
+    while let Some(source) = cause.source() {
+        cause = source;
+    }
+    LhmState::Unreachable(cause.to_string())
+}
+
+fn update_status(
+    ohw: &Mutex<Option<OHWNode>>,
+    status: &Mutex<LhmStatus>,
+    result: Result<OHWNode, LhmState>,
+) {
+    let now = Local::now();
+    let mut s = status.lock();
+    match result {
+        Ok(data) => {
+            *ohw.lock() = Some(data);
+            s.state = LhmState::Connected;
+            s.last_success = Some(now);
+            s.failures = 0;
+            s.next_attempt = None;
+        }
+        Err(state) => {
+            if s.failures == 0 {
+                println!("LHM: {state}");
+            }
+            s.failures += 1;
+            let backoff = (1i64 << (s.failures - 1).min(5)).min(MAX_BACKOFF_SECS);
+            s.next_attempt = Some(now + Duration::seconds(backoff));
+
+            let recent = s
+                .last_success
+                .map(|t| now - t < Duration::seconds(STALE_AFTER_SECS))
+                .unwrap_or(false);
+            if recent {
+                s.state = LhmState::Stale(state.to_string());
+            } else {
+                s.state = state;
+                *ohw.lock() = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    use sysinfo::{System, SystemExt};
+    use tokio::runtime::Runtime;
+
+    use crate::{
+        demo::{spawn_demo_server, DemoArgs, DemoMode},
+        sources::{lhm::LhmSource, MetricSource},
+    };
+
+    #[test]
+    fn reads_the_demo_server_like_lhm() {
+        let rt = Runtime::new().unwrap();
+        let args = DemoArgs::new(DemoMode::Demo, "intel", false, None).unwrap();
+        let lhm = spawn_demo_server(&rt, &args).unwrap();
+        assert_ne!(lhm.port, 0);
+
+        let ohw: Arc<Mutex<Option<OHWNode>>> = Default::default();
+        let status: Arc<Mutex<LhmStatus>> = Default::default();
+        rt.spawn(ohw_thread(
+            ohw.clone(),
+            status.clone(),
+            Default::default(),
+            Some(lhm),
+        ));
+        // the first request goes out right away
+        let started = Instant::now();
+        while ohw.lock().is_none() {
+            assert!(started.elapsed().as_secs() < 5, "no data.json");
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+        assert_eq!(status.lock().state, LhmState::Connected);
+
+        let snapshot = LhmSource::new(ohw).refresh(&mut System::new());
+        let core_temps = snapshot.core_temps.unwrap();
+        assert_eq!(core_temps[..3], [(1, 45.0), (2, 48.0), (3, 51.0)]);
+        let gpus = snapshot.gpus.unwrap();
+        assert_eq!(gpus[0].name, "NVIDIA GeForce RTX 3070");
+    }
+}
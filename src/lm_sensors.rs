@@ -0,0 +1,226 @@
+//! Turns the output of `sensors -j` into the same tree LibreHardwareMonitor serves, so every
+//! LHM reader, the sensor browser and custom tiles work with lm-sensors chips too.
+
+use itertools::Itertools;
+use serde_json::{Map, Value};
+
+use crate::ohw::OHWNode;
+
+/// Chips whose temperatures belong to the CPU.
+const CPU_CHIPS: [&str; 3] = ["coretemp", "k10temp", "zenpower"];
+
+/// Feature prefix, LHM group, LHM sensor type, unit, factor from the lm-sensors value, decimals.
+/// In LHM's group order.
+const KINDS: [(&str, &str, &str, &str, f64, usize); 7] = [
+    ("in", "Voltages", "Voltage", "V", 1.0, 3),
+    ("freq", "Clocks", "Clock", "MHz", 1e-6, 1),
+    ("temp", "Temperatures", "Temperature", "°C", 1.0, 1),
+    ("humidity", "Levels", "Humidity", "%", 1.0, 1),
+    ("fan", "Fans", "Fan", "RPM", 1.0, 0),
+    ("curr", "Currents", "Current", "A", 1.0, 3),
+    ("power", "Powers", "Power", "W", 1.0, 1),
+];
+
+/// `coretemp-isa-0000` -> true
+pub fn is_cpu_chip(chip: &str) -> bool {
+    CPU_CHIPS
+        .iter()
+        .any(|c| chip == *c || chip.starts_with(&format!("{c}-")))
+}
+
+/// Every chip becomes a hardware node with the id `/lm-sensors/<chip>`, every sensor gets the id
+/// `/lm-sensors/<chip>/<feature>`, e.g. `/lm-sensors/nct6798-isa-0290/fan1`. Chips are sorted by
+/// name, sensors by their feature number like `sensors` prints them.
+pub fn sensors_tree(json: &str) -> Result<OHWNode, serde_json::Error> {
+    let chips: Map<String, Value> = serde_json::from_str(json)?;
+
+    let computer = OHWNode {
+        Text: "lm-sensors".to_string(),
+        ImageURL: "images_icon/computer.png".to_string(),
+        Children: chips
+            .iter()
+            .filter_map(|(name, chip)| Some(chip_node(name, chip.as_object()?)))
+            .collect_vec(),
+        ..Default::default()
+    };
+    let mut root = OHWNode {
+        Text: "Sensor".to_string(),
+        Min: "Min".to_string(),
+        Value: "Value".to_string(),
+        Max: "Max".to_string(),
+        Children: vec![computer],
+        ..Default::default()
+    };
+    number_nodes(&mut root, &mut 0);
+    Ok(root)
+}
+
+fn chip_node(name: &str, chip: &Map<String, Value>) -> OHWNode {
+    let hardware_id = format!("/lm-sensors/{name}");
+    let features = chip
+        .iter()
+        .filter_map(|(label, feature)| {
+            let (prefix, index, value) = feature_input(feature.as_object()?)?;
+            Some((label, feature, prefix, index, value))
+        })
+        .collect_vec();
+
+    let groups = KINDS
+        .iter()
+        .filter_map(|&(kind, group, sensor_type, unit, factor, decimals)| {
+            let sensors = features
+                .iter()
+                .filter(|(_, _, prefix, _, _)| *prefix == kind)
+                .sorted_by_key(|(_, _, _, index, _)| *index)
+                .map(|(label, feature, prefix, index, value)| {
+                    let value = value * factor;
+                    let other = |suffix: &str| {
+                        feature
+                            .get(format!("{prefix}{index}_{suffix}"))
+                            .and_then(Value::as_f64)
+                            .map(|v| format!("{:.decimals$} {unit}", v * factor))
+                            .unwrap_or_default()
+                    };
+                    OHWNode {
+                        Text: label.to_string(),
+                        Value: format!("{value:.decimals$} {unit}"),
+                        Min: other("lowest"),
+                        Max: other("highest"),
+                        SensorId: Some(format!("{hardware_id}/{prefix}{index}")),
+                        Type: Some(sensor_type.to_string()),
+                        RawValue: Some(value.into()),
+                        ..Default::default()
+                    }
+                })
+                .collect_vec();
+            (!sensors.is_empty()).then(|| OHWNode {
+                Text: group.to_string(),
+                Children: sensors,
+                ..Default::default()
+            })
+        })
+        .collect_vec();
+
+    OHWNode {
+        Text: name.to_string(),
+        ImageURL: if is_cpu_chip(name) {
+            "images_icon/cpu.png"
+        } else {
+            "images_icon/chip.png"
+        }
+        .to_string(),
+        HardwareId: Some(hardware_id),
+        Children: groups,
+        ..Default::default()
+    }
+}
+
+/// `{"temp2_input": 43.0, "temp2_max": 80.0}` -> ("temp", 2, 43.0). Features without a reading,
+/// like `intrusion0`, are skipped.
+fn feature_input(feature: &Map<String, Value>) -> Option<(&str, u32, f64)> {
+    feature.iter().find_map(|(key, value)| {
+        let name = key.strip_suffix("_input")?;
+        let split = name.find(|c: char| c.is_ascii_digit())?;
+        let (prefix, index) = name.split_at(split);
+        Some((prefix, index.parse().ok()?, value.as_f64()?))
+    })
+}
+
+/// Pre-order ids, like LHM hands them out.
+fn number_nodes(node: &mut OHWNode, next: &mut i64) {
+    node.id = *next;
+    *next += 1;
+    for c in &mut node.Children {
+        number_nodes(c, next);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ohw::MyNode;
+
+    const DESKTOP: &str = include_str!("../fixtures/lm_sensors/desktop_intel.json");
+    const LAPTOP: &str = include_str!("../fixtures/lm_sensors/laptop_amd.json");
+
+    fn texts(nodes: &[OHWNode]) -> Vec<&str> {
+        nodes.iter().map(|n| n.Text.as_str()).collect_vec()
+    }
+
+    #[test]
+    fn desktop_tree_looks_like_lhm() {
+        let root = sensors_tree(DESKTOP).unwrap();
+        assert_eq!((root.Text.as_str(), root.id), ("Sensor", 0));
+        let computer = &root.Children[0];
+        assert_eq!((computer.Text.as_str(), computer.id), ("lm-sensors", 1));
+
+        let coretemp = &computer.Children[0];
+        assert_eq!(coretemp.Text, "coretemp-isa-0000");
+        assert_eq!(coretemp.ImageURL, "images_icon/cpu.png");
+        assert_eq!(
+            coretemp.HardwareId.as_deref(),
+            Some("/lm-sensors/coretemp-isa-0000")
+        );
+        let temps = &coretemp.Children[0];
+        assert_eq!(temps.Text, "Temperatures");
+        assert_eq!(
+            texts(&temps.Children)[..3],
+            ["Package id 0", "Core 0", "Core 1"]
+        );
+        let package = &temps.Children[0];
+        assert_eq!(package.Value, "48.0 °C");
+        assert_eq!(
+            package.SensorId.as_deref(),
+            Some("/lm-sensors/coretemp-isa-0000/temp1")
+        );
+
+        let nct = computer.select("=nct6798-isa-0290").unwrap();
+        assert_eq!(nct.ImageURL, "images_icon/chip.png");
+        // LHM's group order, not the order of the json
+        assert_eq!(texts(&nct.Children)[0], "Voltages");
+        assert_eq!(
+            root.quantity("@/lm-sensors/coretemp-isa-0000/temp3")
+                .unwrap()
+                .value,
+            47.0
+        );
+    }
+
+    #[test]
+    fn laptop_tree_scales_units_and_skips_missing_readings() {
+        let root = sensors_tree(LAPTOP).unwrap();
+        let computer = &root.Children[0];
+        assert_eq!(
+            texts(&computer.Children),
+            [
+                "BAT0-acpi-0",
+                "acpitz-acpi-0",
+                "amdgpu-pci-0400",
+                "k10temp-pci-00c3",
+                "thinkpad-isa-0000"
+            ]
+        );
+        let k10temp = computer.select("=k10temp-pci-00c3").unwrap();
+        assert_eq!(k10temp.ImageURL, "images_icon/cpu.png");
+
+        let sclk = root.select("@/lm-sensors/amdgpu-pci-0400/freq1").unwrap();
+        assert_eq!(sclk.Value, "400.0 MHz");
+        assert_eq!(sclk.Type.as_deref(), Some("Clock"));
+
+        // the GPU temperature reads "N/A"
+        let thinkpad = computer.select("=thinkpad-isa-0000").unwrap();
+        let temps = thinkpad.select("=Temperatures").unwrap();
+        assert_eq!(texts(&temps.Children), ["CPU"]);
+        assert!(root
+            .select("@/lm-sensors/thinkpad-isa-0000/temp2")
+            .is_none());
+    }
+
+    #[test]
+    fn cpu_chips() {
+        assert!(is_cpu_chip("coretemp-isa-0000"));
+        assert!(is_cpu_chip("k10temp-pci-00c3"));
+        assert!(!is_cpu_chip("nct6798-isa-0290"));
+        assert!(!is_cpu_chip("coretempx-isa-0000"));
+    }
+}
@@ -9,119 +9,179 @@ use std::{
     time::Instant,
 };
 
-use crate::{settings::get_screen_size, sidebar::setup_sidebar};
+use crate::settings::get_screen_size;
+use args::Args;
 use chrono::{Duration, Local, NaiveDateTime};
 use circlevec::CircleVec;
+use demo::DemoMode;
 use display_info::DisplayInfo;
 use eframe::{
     egui::{self, Label, Layout, RichText, ScrollArea, Visuals},
     epaint::Color32,
 };
-use ekko::{Ekko, EkkoResponse, EkkoSettings};
-use nvml_wrapper::Nvml;
+use lhm_connection::{ohw_thread, LhmState, LhmStatus};
 use ohw::OHWNode;
 use parking_lot::Mutex;
-use process::{Process, ProcessMetricHandles};
+use ping::{ping_thread, PingHistory};
+use platform::{default_sources, dispose_sidebar, setup_sidebar, show_error};
+use process::Process;
+use recording::{Recorder, ReplaySource};
 use self_update::{backends::github::Update, cargo_crate_version};
 use settings::{show_settings, MySettings};
-use sidebar::dispose_sidebar;
+use sources::{
+    prometheus::{prometheus_thread, PrometheusSource, Scrape},
+    BatteryData, DriveData, MetricSource, NetworkData,
+};
 use sysinfo::{System, SystemExt};
-use system_info::{get_windows_glass_color, init_system, refresh, refresh_color, GpuData};
-use tokio::{runtime::Runtime, time::sleep};
-use windows::Win32::System::Performance::{PdhCloseQuery, PdhOpenQueryA};
+use system_info::{get_glass_color, init_system, refresh, refresh_color, GpuState};
+use tiles::TileState;
+use tokio::runtime::Runtime;
 
-mod autostart;
+mod args;
 mod bytes_format;
 mod circlevec;
 mod color;
 mod components;
+mod demo;
+mod dns;
+mod lhm_connection;
+mod lm_sensors;
 mod ohw;
+mod ping;
+mod platform;
 mod process;
+mod quantity;
+mod recording;
+mod sensor_browser;
 mod settings;
-mod sidebar;
+mod sources;
 mod system_info;
+mod tiles;
 
-// On read problems, run: lodctr /r
 pub const UPDATE_INTERVAL_MILLIS: i64 = 1000;
 pub const INTERNAL_WINDOW_TITLE: &str = "RS_Sidebar\0";
 pub const SIDEBAR_WIDTH: f32 = 130.0;
 
 fn main() -> Result<(), eframe::Error> {
-    let mut pdh_query_handle: isize = -1;
-    unsafe { PdhOpenQueryA(None, 0, &mut pdh_query_handle) };
-
     panic::set_hook(Box::new(|p| {
         println!("Custom panic hook: {p}");
         std::fs::write("error.txt", format!("{p}")).unwrap_or_default();
     }));
 
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            show_error(&e);
+            std::process::exit(1);
+        }
+    };
+    if let Some(demo) = args.demo.clone().filter(|d| d.mode == DemoMode::MockLhm) {
+        if let Err(e) = demo::run_mock_lhm(demo) {
+            println!("{e}");
+        }
+        return Ok(());
+    }
+
     let settings = Arc::new(Mutex::new(MySettings::load()));
     let cancel_settings = settings.clone();
 
     ctrlc::set_handler(move || {
         println!("received Ctrl+C, removing sidebar");
         dispose_sidebar(cancel_settings.clone());
-        unsafe { PdhCloseQuery(pdh_query_handle) };
         std::process::exit(0);
     })
     .expect("Error setting Ctrl-C handler");
 
     let rt = tokio::runtime::Runtime::new().unwrap();
-    let ping_buffer = CircleVec::<u64, 100>::new();
-    let thread_pb = ping_buffer.clone();
+    let pings: Arc<Mutex<Vec<PingHistory>>> = Default::default();
     let ohw_info: Arc<Mutex<Option<OHWNode>>> = Default::default();
     let thread_ohw = ohw_info.clone();
-
-    rt.spawn(ping_thread(thread_pb));
-    rt.spawn(ohw_thread(thread_ohw));
+    let lhm_status: Arc<Mutex<LhmStatus>> = Default::default();
+
+    rt.spawn(ping_thread(pings.clone(), settings.clone()));
+
+    let mut update_interval_ms = UPDATE_INTERVAL_MILLIS;
+    let sources: Vec<Box<dyn MetricSource>> = if let Some(replay) = &args.replay {
+        match ReplaySource::open(&replay.path, ohw_info.clone()) {
+            Ok(source) => {
+                // the recording brings its own LHM tree
+                lhm_status.lock().state = LhmState::Connected;
+                update_interval_ms = (UPDATE_INTERVAL_MILLIS as f64 / replay.speed) as i64;
+                vec![Box::new(source)]
+            }
+            Err(e) => {
+                show_error(&format!("{}: {e}", replay.path.display()));
+                std::process::exit(1);
+            }
+        }
+    } else {
+        let lhm_override = args
+            .demo
+            .and_then(|d| match demo::spawn_demo_server(&rt, &d) {
+                Ok(lhm) => Some(lhm),
+                Err(e) => {
+                    println!("demo server: {e}");
+                    None
+                }
+            });
+        rt.spawn(ohw_thread(
+            thread_ohw,
+            lhm_status.clone(),
+            settings.clone(),
+            lhm_override,
+        ));
+        let scrape: Arc<Mutex<Option<Scrape>>> = Default::default();
+        rt.spawn(prometheus_thread(scrape.clone(), settings.clone()));
+        // a configured node_exporter wins over the local values
+        let mut sources: Vec<Box<dyn MetricSource>> = vec![Box::new(PrometheusSource::new(scrape))];
+        sources.extend(default_sources(ohw_info.clone()));
+        sources
+    };
 
     let update_available = Arc::new(AtomicBool::new(false));
     let thread_update_available = update_available.clone();
     thread::spawn(move || check_update_thread(thread_update_available));
 
-    let nvid_info = if let Ok(n) = Nvml::init() {
-        Some(n)
-    } else {
-        None
-    };
-
     let mut appstate = MyApp {
         system_status: System::new_all(),
-        ping_buffer,
+        pings,
         firstupdate: false,
         framecount: 0,
         next_update: Default::default(),
         next_screen_update: Default::default(),
-        windows_performance_query_handle: pdh_query_handle,
-        disk_time_value_handle_map: Default::default(),
-        core_time_value_handle_map: Default::default(),
+        sources,
+        update_interval_ms,
+        recorder: None,
         cpu_buffer: CircleVec::new(),
         cpu_maxtemp_buffer: CircleVec::new(),
         cpu_power_buffer: CircleVec::new(),
         ram_buffer: CircleVec::new(),
         ohw_info,
+        lhm_status,
         rt,
-        nvid_info,
-        gpu: None,
+        gpus: vec![],
+        tiles: vec![],
         timing: CircleVec::new(),
         current_frame_start: Instant::now(),
         cur_ram: 0.0,
         total_ram: 0.0,
         net_up_buffer: Default::default(),
         net_down_buffer: Default::default(),
-        gpu_buffer: CircleVec::new(),
-        gpu_mem_buffer: CircleVec::new(),
-        gpu_power_buffer: CircleVec::new(),
-        gpu_temp_buffer: CircleVec::new(),
         show_settings: false,
+        sensor_filter: String::new(),
         settings: settings.clone(),
         disk_buffer: Default::default(),
+        drives: vec![],
+        networks: vec![],
+        core_usage: vec![],
+        core_temps: vec![],
+        cpu_power_limit: None,
         processes: vec![],
-        process_metric_handles: Default::default(),
         update_available,
         battery_change_buffer: CircleVec::new(),
         battery_level_buffer: CircleVec::new(),
         battery_enabled: false,
+        battery: None,
         battery_level_next_update: Default::default(),
     };
 
@@ -163,7 +223,7 @@ fn main() -> Result<(), eframe::Error> {
         Box::new(move |cc| {
             let mut v = Visuals::dark();
             v.override_text_color = Some(Color32::from_gray(250));
-            v.window_fill = get_windows_glass_color(use_plain_background);
+            v.window_fill = get_glass_color(use_plain_background);
             cc.egui_ctx.set_visuals(v);
             Box::new(appstate)
         }),
@@ -171,63 +231,25 @@ fn main() -> Result<(), eframe::Error> {
 
     dispose_sidebar(settings.clone());
 
-    unsafe { PdhCloseQuery(pdh_query_handle) };
-
     Ok(())
 }
 
-async fn ping_thread(thread_pb: Arc<CircleVec<u64, 100>>) -> ! {
-    let ekko = Ekko::with_target([8, 8, 8, 8]).unwrap();
-    loop {
-        if let Ok(res) = ekko.send_with_settings(
-            32,
-            EkkoSettings {
-                timeout: std::time::Duration::from_millis(950),
-                ..Default::default()
-            },
-        ) {
-            match res {
-                EkkoResponse::Destination(res) => thread_pb.add(res.elapsed.as_millis() as u64),
-                _ => thread_pb.add(0),
-            }
-        } else {
-            thread_pb.add(0)
-        }
-
-        sleep(
-            Duration::milliseconds(
-                (1000 - Local::now().naive_local().timestamp_subsec_millis() as i64)
-                    .min(1000)
-                    .max(520),
-            )
-            .to_std()
-            .unwrap_or(std::time::Duration::from_secs(1)),
-        )
-        .await;
+/// Explains why LHM values are missing. Without LHM on Linux, only a lost connection is shown.
+fn show_lhm_status(appdata: &MyApp, ui: &mut egui::Ui) {
+    let status = appdata.lhm_status.lock();
+    if !platform::LHM_REQUIRED && status.last_success.is_none() {
+        return;
     }
-}
-
-async fn ohw_thread(thread_ohw: Arc<Mutex<Option<OHWNode>>>) -> ! {
-    loop {
-        if let Ok(data) = reqwest::get("http://localhost:8085/data.json").await {
-            if let Ok(data) = data.json::<OHWNode>().await {
-                *thread_ohw.lock() = Some(data)
-            } else {
-                *thread_ohw.lock() = None
-            }
-        } else {
-            *thread_ohw.lock() = None
-        };
-        sleep(
-            Duration::milliseconds(
-                (1000 - Local::now().naive_local().timestamp_subsec_millis() as i64)
-                    .min(999)
-                    .max(520),
-            )
-            .to_std()
-            .unwrap(),
-        )
-        .await;
+    if let Some(summary) = status.summary() {
+        let last = status
+            .last_success
+            .map(|t| t.format("%H:%M:%S").to_string())
+            .unwrap_or_else(|| "never".to_string());
+        ui.add(Label::new(
+            RichText::new(summary).small().color(Color32::YELLOW),
+        ))
+        .on_hover_text(format!("Last data: {last}"));
+        ui.separator();
     }
 }
 
@@ -236,7 +258,7 @@ fn check_update_thread(update_available: Arc<AtomicBool>) -> ! {
         if let Ok(status) = Update::configure()
             .repo_owner("chrisheib")
             .repo_name("ststat")
-            .bin_name("ststat.exe")
+            .bin_name(platform::BIN_NAME)
             .current_version(cargo_crate_version!())
             .build()
         {
@@ -265,6 +287,8 @@ pub enum CurrentStep {
     UpdateSystemProcess,
     UpdateBattery,
     UpdateIoTime,
+    UpdateTiles,
+    UpdateSource(&'static str),
     Update,
     CpuCrunch,
     CPU,
@@ -274,6 +298,7 @@ pub enum CurrentStep {
     Ping,
     Network,
     GPU,
+    Tiles,
 }
 
 #[derive(Debug, Default, Clone, Copy)]
@@ -288,37 +313,40 @@ pub struct MyApp {
     pub system_status: System,
     pub next_update: NaiveDateTime,
     pub next_screen_update: NaiveDateTime,
-    pub ping_buffer: Arc<CircleVec<u64, 100>>,
+    pub pings: Arc<Mutex<Vec<PingHistory>>>,
     pub cpu_buffer: Arc<CircleVec<f32, 100>>,
     pub cpu_maxtemp_buffer: Arc<CircleVec<f32, 100>>,
     pub cpu_power_buffer: Arc<CircleVec<f64, 100>>,
     pub ram_buffer: Arc<CircleVec<f32, 100>>,
-    pub windows_performance_query_handle: isize,
-    pub disk_time_value_handle_map: Vec<(String, isize, f64)>,
-    pub core_time_value_handle_map: Vec<(usize, isize, f64)>,
-    pub nvid_info: Option<Nvml>,
+    pub sources: Vec<Box<dyn MetricSource>>,
     pub ohw_info: Arc<Mutex<Option<OHWNode>>>,
+    pub lhm_status: Arc<Mutex<LhmStatus>>,
     pub rt: Runtime,
-    pub gpu: Option<GpuData>,
+    pub gpus: Vec<GpuState>,
+    pub tiles: Vec<TileState>,
     pub timing: Arc<CircleVec<TimingStep, 2000>>,
+    pub update_interval_ms: i64,
+    pub recorder: Option<Recorder>,
     pub current_frame_start: Instant,
     pub cur_ram: f32,
     pub total_ram: f32,
     pub net_up_buffer: HashMap<String, Arc<CircleVec<f64, 100>>>,
     pub net_down_buffer: HashMap<String, Arc<CircleVec<f64, 100>>>,
-    pub gpu_buffer: Arc<CircleVec<f64, 100>>,
-    pub gpu_mem_buffer: Arc<CircleVec<f64, 100>>,
-    pub gpu_power_buffer: Arc<CircleVec<f64, 100>>,
-    pub gpu_temp_buffer: Arc<CircleVec<f64, 100>>,
     pub show_settings: bool,
+    pub sensor_filter: String,
     pub settings: Arc<Mutex<MySettings>>,
     pub disk_buffer: HashMap<String, Arc<CircleVec<f64, 100>>>,
+    pub drives: Vec<DriveData>,
+    pub networks: Vec<(String, NetworkData)>,
+    pub core_usage: Vec<f32>,
+    pub core_temps: Vec<(i32, f32)>,
+    pub cpu_power_limit: Option<f64>,
     pub processes: Vec<Process>,
-    pub process_metric_handles: ProcessMetricHandles,
     pub update_available: Arc<AtomicBool>,
     pub battery_change_buffer: Arc<CircleVec<f64, 120>>,
     pub battery_level_buffer: Arc<CircleVec<f64, 120>>,
     pub battery_enabled: bool,
+    pub battery: Option<BatteryData>,
     pub battery_level_next_update: NaiveDateTime,
 }
 
@@ -339,8 +367,11 @@ impl eframe::App for MyApp {
         if now > self.next_update {
             refresh(self);
             step_timing(self, CurrentStep::Update);
-            self.next_update =
-                now + Duration::milliseconds(1000i64 - now.timestamp_subsec_millis() as i64);
+            self.next_update = if self.update_interval_ms == UPDATE_INTERVAL_MILLIS {
+                now + Duration::milliseconds(1000i64 - now.timestamp_subsec_millis() as i64)
+            } else {
+                now + Duration::milliseconds(self.update_interval_ms)
+            };
             update = true;
         }
 
@@ -362,7 +393,7 @@ impl eframe::App for MyApp {
         if !self.firstupdate && self.framecount > 1 {
             println!("Setup sidebar");
             self.firstupdate = true;
-            sidebar::setup_sidebar(self, scale_override);
+            setup_sidebar(self, scale_override);
             let s = self.settings.lock();
             frame.set_window_pos(
                 (s.current_settings.location.x, s.current_settings.location.y).into(),
@@ -382,7 +413,7 @@ impl eframe::App for MyApp {
             frame.set_window_pos(set_pos.into());
             frame.set_window_size(size.into());
             let scale_override = frame.info().native_pixels_per_point;
-            setup_sidebar(&self, scale_override);
+            setup_sidebar(self, scale_override);
         }
 
         let use_plain_background = self
@@ -425,6 +456,8 @@ impl eframe::App for MyApp {
                 ui.separator();
             }
 
+            show_lhm_status(self, ui);
+
             ScrollArea::vertical().show(ui, |ui| {
                 system_info::set_system_info_components(self, ui);
                 ui.checkbox(&mut self.show_settings, "Show settings");
@@ -433,12 +466,17 @@ impl eframe::App for MyApp {
             });
 
             let time_to_next_second = 1000 - chrono::Local::now().timestamp_subsec_millis();
+            let time_to_next_update = (self.next_update - Local::now().naive_local())
+                .num_milliseconds()
+                .max(0);
 
             // guess when the next update should occur.
             ctx.request_repaint_after(
-                (chrono::Duration::milliseconds(time_to_next_second as i64 + 5))
-                    .to_std()
-                    .unwrap(),
+                (chrono::Duration::milliseconds(
+                    (time_to_next_second as i64).min(time_to_next_update) + 5,
+                ))
+                .to_std()
+                .unwrap(),
             );
         });
     }
@@ -454,7 +492,7 @@ fn custom_window_frame(
     use egui::*;
 
     let panel_frame = egui::Frame {
-        fill: { get_windows_glass_color(use_plain_background) },
+        fill: { get_glass_color(use_plain_background) },
         // rounding: 10.0.into(),
         stroke: Stroke {
             width: 0.0,
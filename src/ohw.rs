@@ -1,8 +1,11 @@
 use itertools::Itertools;
-use serde::Deserialize;
-use std::str::FromStr;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
 
-#[derive(Deserialize, Default, Debug, Clone)]
+use crate::quantity::{sensor_type_unit, Quantity, QuantityError, Unit};
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone)]
 #[allow(dead_code)]
 #[allow(non_snake_case)]
 pub struct OHWNode {
@@ -13,88 +16,717 @@ pub struct OHWNode {
     pub Text: String,
     pub Value: String,
     pub id: i64,
+    /// Stable sensor identifier like `/amdcpu/0/temperature/2`, only sent by newer LHM versions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub SensorId: Option<String>,
+    /// Hardware identifier like `/gpu-nvidia/0`, only sent by newer LHM versions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub HardwareId: Option<String>,
+    /// LHM `SensorType`, e.g. `Temperature` or `SmallData`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Type: Option<String>,
+    /// Unformatted value in the sensor type's unit. Some versions send a number, some a string.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub RawValue: Option<serde_json::Value>,
+}
+
+/// Why a sensor value could not be read.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SensorError {
+    NotFound(String),
+    Parse {
+        path: String,
+        error: QuantityError,
+    },
+    WrongUnit {
+        path: String,
+        expected: Unit,
+        found: Unit,
+    },
+    /// An aggregate over sensors that don't measure the same thing.
+    MixedUnits(String),
+}
+
+impl Display for SensorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SensorError::NotFound(path) => write!(f, "{path}: no such sensor"),
+            SensorError::MixedUnits(path) => write!(f, "{path}: sensors have different units"),
+            SensorError::Parse { path, error } => write!(f, "{path}: {error}"),
+            SensorError::WrongUnit {
+                path,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{path}: expected a value in '{}', got '{}'",
+                expected.symbol(),
+                found.symbol()
+            ),
+        }
+    }
 }
 
+impl std::error::Error for SensorError {}
+
+/// Sensor lookup by path. A path is a list of segments separated by `|`, each one selecting
+/// among the children of the nodes matched so far:
+///
+/// - `#2`: the child at index 2
+/// - `+images_icon/cpu.png`: the first child with that ImageURL
+/// - `=CPU Package`: the first child with exactly that Text
+/// - `@/amdcpu/0/temperature/2`: the first descendant with that SensorId or HardwareId
+/// - `~^CPU Core #\d+$`: every child whose Text matches the regex
+/// - `CPU Core #*`: every child whose Text matches the glob, case-insensitive. `*` alone matches
+///   all children.
+/// - anything else: the first child whose Text contains the segment, case-insensitive
+///
+/// `\|` is a `|` inside a segment instead of a separator, e.g. `~^(CPU\|GPU) Core`.
+///
+/// [`MyNode::quantity`] additionally accepts `max(path)`, `min(path)`, `avg(path)`, `sum(path)`
+/// and `count(path)` over every node `path` matches.
 pub trait MyNode {
-    fn parse_value_path<T: FromStr + Default>(&self, path: &str) -> Option<T>;
+    fn select_parsed(&self, selector: &Selector) -> Vec<&OHWNode>;
 
-    fn select(&self, path: &str) -> Option<&OHWNode>;
+    fn select_all(&self, path: &str) -> Vec<&OHWNode> {
+        self.select_parsed(&Selector::parse(path))
+    }
 
-    fn parse_value_path_def<T: FromStr + Default>(&self, path: &str) -> T;
-}
+    fn select(&self, path: &str) -> Option<&OHWNode> {
+        self.select_all(path).into_iter().next()
+    }
 
-impl MyNode for Option<OHWNode> {
-    fn parse_value_path<T: FromStr + Default>(&self, path: &str) -> Option<T> {
-        if let Some(n) = self {
-            n.parse_value_path(path)
+    /// The value at `path`, converted to its base unit.
+    fn quantity(&self, path: &str) -> Result<Quantity, SensorError> {
+        self.quantity_parsed(&Selector::parse(path))
+    }
+
+    /// Like [`MyNode::quantity`], for a path that is read more than once.
+    fn quantity_parsed(&self, selector: &Selector) -> Result<Quantity, SensorError> {
+        let path = &selector.path;
+        let nodes = self.select_parsed(selector);
+        let Some(aggregate) = selector.aggregate else {
+            let n = nodes
+                .into_iter()
+                .next()
+                .ok_or_else(|| SensorError::NotFound(path.to_string()))?;
+            return parse_node(n, path);
+        };
+
+        if nodes.is_empty() {
+            return Err(SensorError::NotFound(path.to_string()));
+        }
+        if aggregate == Aggregate::Count {
+            return Ok(Quantity {
+                value: nodes.len() as f64,
+                unit: Unit::None,
+            });
+        }
+        let inner = &selector.inner;
+        let values = nodes
+            .iter()
+            .map(|n| parse_node(n, &format!("{inner}|={}", n.Text)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let unit = values[0].unit;
+        if values.iter().any(|q| q.unit != unit) {
+            return Err(SensorError::MixedUnits(path.to_string()));
+        }
+        Ok(Quantity {
+            value: aggregate.apply(values.iter().map(|q| q.value)),
+            unit,
+        })
+    }
+
+    /// Like [`MyNode::quantity`], but fails if the sensor does not measure `unit`.
+    fn value(&self, path: &str, unit: Unit) -> Result<f64, SensorError> {
+        let q = self.quantity(path)?;
+        if q.unit == unit {
+            Ok(q.value)
         } else {
-            None
+            Err(SensorError::WrongUnit {
+                path: path.to_string(),
+                expected: unit,
+                found: q.unit,
+            })
         }
     }
+}
 
-    fn parse_value_path_def<T: FromStr + Default>(&self, path: &str) -> T {
-        if let Some(n) = self {
-            n.parse_value_path(path).unwrap_or_default()
+/// A parsed path, see [`MyNode`]. Regex segments are compiled here, keep the selector around
+/// for paths that are read on every refresh.
+#[derive(Debug, Clone)]
+pub struct Selector {
+    path: String,
+    aggregate: Option<Aggregate>,
+    /// The path inside the aggregate, or the whole path
+    inner: String,
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone)]
+enum Segment {
+    All,
+    Index(usize),
+    Image(String),
+    Id(String),
+    Text(String),
+    /// `None` if the regex doesn't compile, it matches nothing like any other unknown sensor.
+    Regex(Option<Regex>),
+    /// Lowercase
+    Glob(String),
+    /// Lowercase
+    Contains(String),
+}
+
+impl Selector {
+    pub fn parse(path: &str) -> Self {
+        let (aggregate, inner) = match Aggregate::parse(path) {
+            Some((aggregate, inner)) => (Some(aggregate), inner),
+            None => (None, path),
+        };
+        Self {
+            path: path.to_string(),
+            aggregate,
+            inner: inner.to_string(),
+            segments: split_path(inner)
+                .iter()
+                .filter(|p| !p.is_empty())
+                .map(|p| Segment::parse(p))
+                .collect_vec(),
+        }
+    }
+}
+
+/// Splits at every `|` that isn't escaped as `\|`.
+fn split_path(path: &str) -> Vec<String> {
+    let mut segments = vec![String::new()];
+    let mut chars = path.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'|') => {
+                chars.next();
+                segments.last_mut().unwrap().push('|');
+            }
+            '|' => segments.push(String::new()),
+            c => segments.last_mut().unwrap().push(c),
+        }
+    }
+    segments
+}
+
+impl Segment {
+    fn parse(p: &str) -> Self {
+        if p == "*" {
+            Segment::All
+        } else if let Some(idx) = p.strip_prefix('#') {
+            // an index that doesn't parse selects nothing
+            Segment::Index(idx.parse().unwrap_or(usize::MAX))
+        } else if let Some(url) = p.strip_prefix('+') {
+            Segment::Image(url.to_string())
+        } else if let Some(id) = p.strip_prefix('@') {
+            Segment::Id(id.to_string())
+        } else if let Some(text) = p.strip_prefix('=') {
+            Segment::Text(text.to_string())
+        } else if let Some(re) = p.strip_prefix('~') {
+            Segment::Regex(Regex::new(re).ok())
+        } else if p.contains(['*', '?']) {
+            Segment::Glob(p.to_lowercase())
         } else {
-            Default::default()
+            Segment::Contains(p.to_lowercase())
         }
     }
+}
 
-    fn select(&self, path: &str) -> Option<&OHWNode> {
+impl MyNode for Option<OHWNode> {
+    fn select_parsed(&self, selector: &Selector) -> Vec<&OHWNode> {
         if let Some(n) = self {
-            n.select(path)
+            n.select_parsed(selector)
         } else {
-            None
+            vec![]
         }
     }
 }
 
 impl MyNode for OHWNode {
-    fn parse_value_path<T: FromStr + Default>(&self, path: &str) -> Option<T> {
-        if let Some(n) = self.select(path) {
-            let v = n
-                .Value
-                .replace("°C", "")
-                .replace('%', "")
-                .replace("MHz", "")
-                .replace('W', "")
-                .replace("MB", "")
-                .replace("GB", "")
-                .replace('A', "")
-                .replace(',', ".");
-            let v = v.trim();
-
-            Some(v.parse::<T>().unwrap_or_default())
-        } else {
-            None
+    fn select_parsed(&self, selector: &Selector) -> Vec<&OHWNode> {
+        let mut current = vec![self];
+        for segment in &selector.segments {
+            current = current
+                .into_iter()
+                .flat_map(|n| n.matching_children(segment))
+                .collect_vec();
         }
+        current
     }
+}
 
-    fn select(&self, path: &str) -> Option<&OHWNode> {
-        let paths = path.split('|').collect_vec();
-        let mut current = Some(self);
-        for p in paths {
-            if p.starts_with('#') {
-                if let Ok(idx) = p.replace('#', "").parse::<usize>() {
-                    current = current.and_then(|n| n.Children.get(idx));
+impl OHWNode {
+    /// Depth first search for a sensor or hardware id.
+    pub fn find_id(&self, id: &str) -> Option<&OHWNode> {
+        self.Children.iter().find_map(|n| {
+            if n.SensorId.as_deref() == Some(id) || n.HardwareId.as_deref() == Some(id) {
+                Some(n)
+            } else {
+                n.find_id(id)
+            }
+        })
+    }
+
+    /// The value from `RawValue` and `Type`, which doesn't depend on the locale LHM formats
+    /// `Value` in.
+    fn raw_quantity(&self) -> Option<Quantity> {
+        let (unit, factor) = sensor_type_unit(self.Type.as_deref()?)?;
+        let value = match self.RawValue.as_ref()? {
+            serde_json::Value::Number(n) => n.as_f64()? * factor,
+            serde_json::Value::String(s) => {
+                let q = s.parse::<Quantity>().ok()?;
+                if q.unit == Unit::None {
+                    q.value * factor
+                } else if q.unit == unit {
+                    q.value
                 } else {
-                    current = None;
+                    return None;
                 }
-            } else if p.starts_with('+') {
-                current = current
-                    .and_then(|n| n.Children.iter().find(|n| n.ImageURL == p.replace('+', "")));
-            } else {
-                current = current.and_then(|n| {
-                    n.Children
-                        .iter()
-                        .find(|n| n.Text.to_lowercase().contains(&p.to_lowercase()))
-                });
             }
+            _ => return None,
+        };
+        Some(Quantity { value, unit })
+    }
+
+    fn matching_children(&self, segment: &Segment) -> Vec<&OHWNode> {
+        let mut children = self.Children.iter();
+        match segment {
+            Segment::All => children.collect_vec(),
+            Segment::Index(idx) => self.Children.get(*idx).into_iter().collect_vec(),
+            Segment::Image(url) => children
+                .find(|n| &n.ImageURL == url)
+                .into_iter()
+                .collect_vec(),
+            Segment::Id(id) => self.find_id(id).into_iter().collect_vec(),
+            Segment::Text(text) => children.find(|n| &n.Text == text).into_iter().collect_vec(),
+            Segment::Regex(Some(re)) => children.filter(|n| re.is_match(&n.Text)).collect_vec(),
+            Segment::Regex(None) => vec![],
+            Segment::Glob(p) => children
+                .filter(|n| glob_match(p, &n.Text.to_lowercase()))
+                .collect_vec(),
+            Segment::Contains(p) => children
+                .find(|n| n.Text.to_lowercase().contains(p))
+                .into_iter()
+                .collect_vec(),
         }
-        current
+    }
+}
+
+fn parse_node(n: &OHWNode, path: &str) -> Result<Quantity, SensorError> {
+    if let Some(q) = n.raw_quantity() {
+        return Ok(q);
+    }
+    n.Value.parse().map_err(|error| SensorError::Parse {
+        path: path.to_string(),
+        error,
+    })
+}
+
+/// `*` matches any run of characters, `?` exactly one.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let p = pattern.chars().collect_vec();
+    let t = text.chars().collect_vec();
+    let (mut pi, mut ti) = (0, 0);
+    // Position of the last `*` and the text position it currently swallows up to.
+    let mut star: Option<(usize, usize)> = None;
+    while ti < t.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == t[ti]) {
+            pi += 1;
+            ti += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ti));
+            pi += 1;
+        } else if let Some((sp, st)) = star {
+            pi = sp + 1;
+            ti = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|c| *c == '*')
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Aggregate {
+    Min,
+    Max,
+    Avg,
+    Sum,
+    Count,
+}
+
+impl Aggregate {
+    /// Splits `max(path)` into the function and the inner path.
+    fn parse(path: &str) -> Option<(Aggregate, &str)> {
+        let (func, rest) = path.trim().split_once('(')?;
+        let inner = rest.strip_suffix(')')?;
+        let aggregate = match func.trim() {
+            "min" => Aggregate::Min,
+            "max" => Aggregate::Max,
+            "avg" => Aggregate::Avg,
+            "sum" => Aggregate::Sum,
+            "count" => Aggregate::Count,
+            _ => return None,
+        };
+        Some((aggregate, inner))
+    }
+
+    fn apply(self, values: impl Iterator<Item = f64>) -> f64 {
+        let values = values.collect_vec();
+        match self {
+            Aggregate::Min => values.iter().copied().fold(f64::INFINITY, f64::min),
+            Aggregate::Max => values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            Aggregate::Avg => values.iter().sum::<f64>() / values.len().max(1) as f64,
+            Aggregate::Sum => values.iter().sum(),
+            Aggregate::Count => values.len() as f64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(text: &str, value: &str, children: Vec<OHWNode>) -> OHWNode {
+        OHWNode {
+            Text: text.to_string(),
+            Value: value.to_string(),
+            Children: children,
+            ..Default::default()
+        }
+    }
+
+    fn tree() -> OHWNode {
+        node(
+            "Sensor",
+            "",
+            vec![node(
+                "PC",
+                "",
+                vec![
+                    node("CPU Core", "60 °C", vec![]),
+                    node("GPU Core", "70 °C", vec![]),
+                    node("SoC|Core", "50 °C", vec![]),
+                ],
+            )],
+        )
+    }
+
+    #[test]
+    fn escaped_pipe_stays_in_the_segment() {
+        assert_eq!(
+            split_path(r"PC|~^(CPU\|GPU) Core$"),
+            ["PC", "~^(CPU|GPU) Core$"]
+        );
+        let tree = tree();
+        let texts = tree
+            .select_all(r"PC|~^(CPU\|GPU) Core$")
+            .iter()
+            .map(|n| n.Text.as_str())
+            .collect_vec();
+        assert_eq!(texts, ["CPU Core", "GPU Core"]);
+        assert_eq!(tree.select(r"PC|=SoC\|Core").unwrap().Value, "50 °C");
+    }
+
+    #[test]
+    fn regex_is_compiled_once() {
+        let selector = Selector::parse(r"max(PC|~CPU\|GPU)");
+        assert!(matches!(selector.segments[1], Segment::Regex(Some(_))));
+        let tree = tree();
+        for _ in 0..2 {
+            assert_eq!(tree.quantity_parsed(&selector).unwrap().value, 70.0);
+        }
+    }
+
+    #[test]
+    fn invalid_regex_matches_nothing() {
+        assert_eq!(
+            tree().quantity("PC|~(CPU"),
+            Err(SensorError::NotFound("PC|~(CPU".to_string()))
+        );
+    }
+
+    /// Like LHM: computer, hardware, sensor groups, sensors.
+    fn lhm_tree() -> OHWNode {
+        let cpu = OHWNode {
+            ImageURL: "images_icon/cpu.png".to_string(),
+            ..node(
+                "Intel Core i7",
+                "",
+                vec![
+                    node(
+                        "Temperatures",
+                        "",
+                        vec![
+                            node("CPU Core #1", "50,0 °C", vec![]),
+                            node("CPU Core #2", "60.0 °C", vec![]),
+                            node("CPU Package", "64.0 °C", vec![]),
+                            node("Core Max", "62.0 °C", vec![]),
+                        ],
+                    ),
+                    node(
+                        "Load",
+                        "",
+                        vec![
+                            node("CPU Total", "25.0 %", vec![]),
+                            node("CPU Core #1", "30.0 %", vec![]),
+                        ],
+                    ),
+                ],
+            )
+        };
+        let board = node("ASUS PRIME", "", vec![]);
+        node("Sensor", "", vec![node("PC", "", vec![board, cpu])])
+    }
+
+    fn texts(nodes: Vec<&OHWNode>) -> Vec<&str> {
+        nodes.into_iter().map(|n| n.Text.as_str()).collect_vec()
+    }
+
+    #[test]
+    fn globs_and_wildcards() {
+        let tree = lhm_tree();
+        assert_eq!(
+            texts(tree.select_all("PC|+images_icon/cpu.png|Temperatures|cpu core #*")),
+            ["CPU Core #1", "CPU Core #2"]
+        );
+        assert_eq!(
+            texts(tree.select_all("PC|#1|Temperatures|C??e*")),
+            ["Core Max"]
+        );
+        assert_eq!(
+            texts(tree.select_all("PC|*")),
+            ["ASUS PRIME", "Intel Core i7"]
+        );
+        assert_eq!(texts(tree.select_all("PC|*|*|CPU Core #1")).len(), 2);
+    }
+
+    #[test]
+    fn exact_text_contains_and_index() {
+        let tree = lhm_tree();
+        assert_eq!(
+            tree.select("PC|Intel|Temperatures|=CPU Package")
+                .unwrap()
+                .Value,
+            "64.0 °C"
+        );
+        // `=` wants the whole text, without it the first child containing the segment wins
+        assert!(tree.select("PC|Intel|Temperatures|=CPU").is_none());
+        assert_eq!(
+            tree.select("PC|Intel|Temperatures|cpu").unwrap().Text,
+            "CPU Core #1"
+        );
+        assert_eq!(tree.select("PC|#1|#0|#3").unwrap().Text, "Core Max");
+        assert!(tree.select("PC|#1|#0|#9").is_none());
+        assert!(tree.select("PC|#x").is_none());
+    }
+
+    #[test]
+    fn aggregates() {
+        let tree = lhm_tree();
+        let path = |f: &str| format!("{f}(PC|Intel|Temperatures|CPU Core #*)");
+        let value = |f: &str| tree.quantity(&path(f)).unwrap();
+        assert_eq!(value("max").value, 60.0);
+        assert_eq!(value("max").unit, Unit::Celsius);
+        assert_eq!(value("min").value, 50.0);
+        assert_eq!(value("avg").value, 55.0);
+        assert_eq!(value("sum").value, 110.0);
+        assert_eq!(
+            value("count"),
+            Quantity {
+                value: 2.0,
+                unit: Unit::None
+            }
+        );
+        assert_eq!(
+            tree.quantity("max(PC|Intel|Temperatures|GPU*)"),
+            Err(SensorError::NotFound(
+                "max(PC|Intel|Temperatures|GPU*)".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn aggregate_over_different_units_fails() {
+        let tree = lhm_tree();
+        assert_eq!(
+            tree.quantity("max(PC|Intel|*|CPU Core #1)"),
+            Err(SensorError::MixedUnits(
+                "max(PC|Intel|*|CPU Core #1)".to_string()
+            ))
+        );
+        // counting doesn't need a common unit
+        assert_eq!(
+            tree.quantity("count(PC|Intel|*|CPU Core #1)")
+                .unwrap()
+                .value,
+            2.0
+        );
+    }
+
+    #[test]
+    fn value_checks_the_unit() {
+        let tree = lhm_tree();
+        assert_eq!(
+            tree.value("PC|Intel|Load|CPU Total", Unit::Percent),
+            Ok(25.0)
+        );
+        assert_eq!(
+            tree.value("PC|Intel|Load|CPU Total", Unit::Celsius),
+            Err(SensorError::WrongUnit {
+                path: "PC|Intel|Load|CPU Total".to_string(),
+                expected: Unit::Celsius,
+                found: Unit::Percent,
+            })
+        );
+    }
+
+    fn sensor(text: &str, id: &str, ty: &str, raw: serde_json::Value, value: &str) -> OHWNode {
+        OHWNode {
+            SensorId: Some(id.to_string()),
+            Type: Some(ty.to_string()),
+            RawValue: Some(raw),
+            ..node(text, value, vec![])
+        }
+    }
+
+    /// Like newer LHM versions, with ids, types and raw values.
+    fn id_tree() -> OHWNode {
+        let gpu = OHWNode {
+            HardwareId: Some("/gpu-nvidia/0".to_string()),
+            ..node(
+                "NVIDIA GeForce RTX 3080",
+                "",
+                vec![
+                    node(
+                        "Clocks",
+                        "",
+                        vec![sensor(
+                            "GPU Core",
+                            "/gpu-nvidia/0/clock/0",
+                            "Clock",
+                            serde_json::json!(1905.0),
+                            "1905,0 MHz",
+                        )],
+                    ),
+                    node(
+                        "Data",
+                        "",
+                        vec![
+                            sensor(
+                                "GPU Memory Used",
+                                "/gpu-nvidia/0/smalldata/1",
+                                "SmallData",
+                                serde_json::json!(2.0),
+                                "2,0 MB",
+                            ),
+                            sensor(
+                                "GPU Memory Total",
+                                "/gpu-nvidia/0/smalldata/2",
+                                "SmallData",
+                                serde_json::json!("10240"),
+                                "10,0 GB",
+                            ),
+                        ],
+                    ),
+                    sensor(
+                        "GPU Core",
+                        "/gpu-nvidia/0/temperature/0",
+                        "Temperature",
+                        serde_json::json!("45.5 °C"),
+                        "45,5 °C",
+                    ),
+                ],
+            )
+        };
+        node("Sensor", "", vec![node("PC", "", vec![gpu])])
+    }
+
+    #[test]
+    fn selects_by_sensor_and_hardware_id() {
+        let tree = id_tree();
+        assert_eq!(
+            tree.find_id("/gpu-nvidia/0").unwrap().Text,
+            "NVIDIA GeForce RTX 3080"
+        );
+        assert_eq!(
+            tree.find_id("/gpu-nvidia/0/smalldata/2").unwrap().Text,
+            "GPU Memory Total"
+        );
+        assert_eq!(
+            tree.select("@/gpu-nvidia/0/clock/0").unwrap().Value,
+            "1905,0 MHz"
+        );
+        // the id search starts below the nodes matched so far
+        assert_eq!(
+            tree.select("PC|@/gpu-nvidia/0|Clocks|#0").unwrap().Text,
+            "GPU Core"
+        );
+        assert_eq!(
+            texts(tree.select_all("@/gpu-nvidia/0|Data|*")),
+            ["GPU Memory Used", "GPU Memory Total"]
+        );
+    }
+
+    #[test]
+    fn missing_id() {
+        let tree = id_tree();
+        assert!(tree.find_id("/gpu-nvidia/1").is_none());
+        assert!(tree.select("@/gpu-nvidia/1").is_none());
+        assert_eq!(
+            tree.quantity("@/gpu-nvidia/0/clock/9"),
+            Err(SensorError::NotFound("@/gpu-nvidia/0/clock/9".to_string()))
+        );
+    }
+
+    #[test]
+    fn raw_values_use_the_type_factor() {
+        let tree = id_tree();
+        // number, in MHz
+        assert_eq!(
+            tree.quantity("@/gpu-nvidia/0/clock/0"),
+            Ok(Quantity {
+                value: 1905e6,
+                unit: Unit::Hertz
+            })
+        );
+        // number, in MiB
+        assert_eq!(
+            tree.value("@/gpu-nvidia/0/smalldata/1", Unit::Byte),
+            Ok(2.0 * 1024.0 * 1024.0)
+        );
+        // string without unit, in MiB
+        assert_eq!(
+            tree.value("@/gpu-nvidia/0/smalldata/2", Unit::Byte),
+            Ok(10240.0 * 1024.0 * 1024.0)
+        );
+        // string with the unit of the type
+        assert_eq!(
+            tree.value("@/gpu-nvidia/0/temperature/0", Unit::Celsius),
+            Ok(45.5)
+        );
     }
 
-    fn parse_value_path_def<T: FromStr + Default>(&self, path: &str) -> T {
-        self.parse_value_path(path).unwrap_or_default()
+    #[test]
+    fn falls_back_to_the_formatted_value() {
+        let unknown_type = sensor("Noise", "/noise/0", "Noise", serde_json::json!(3.0), "40 %");
+        let wrong_unit = sensor(
+            "Fan",
+            "/fan/0",
+            "Fan",
+            serde_json::json!("12 V"),
+            "1200 RPM",
+        );
+        let no_number = sensor("Load", "/load/0", "Load", serde_json::json!(null), "7,5 %");
+        let tree = node("Sensor", "", vec![unknown_type, wrong_unit, no_number]);
+        assert_eq!(tree.value("@/noise/0", Unit::Percent), Ok(40.0));
+        assert_eq!(tree.value("@/fan/0", Unit::Rpm), Ok(1200.0));
+        assert_eq!(tree.value("@/load/0", Unit::Percent), Ok(7.5));
     }
 }
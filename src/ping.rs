@@ -0,0 +1,431 @@
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::Instant,
+};
+
+use ekko::{Ekko, EkkoResponse, EkkoSettings};
+use itertools::Itertools;
+use parking_lot::Mutex;
+use tokio::{
+    net::{lookup_host, TcpStream},
+    task::JoinHandle,
+    time::sleep,
+};
+
+use crate::{
+    circlevec::CircleVec,
+    dns::{self, DnsAnswer},
+    settings::{MySettings, PingTarget, Probe},
+};
+
+/// The outcome of one ping.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PingResult {
+    /// Nothing sent yet, the history isn't full.
+    #[default]
+    Empty,
+    /// Round trip time in ms
+    Reply(f64),
+    Timeout,
+    /// A router on the way has no route to the host, or nothing listens on a probed port.
+    Unreachable,
+    /// The request ran out of hops before it arrived.
+    TtlExceeded,
+    /// The host name didn't resolve or the request couldn't be sent, e.g. without a network.
+    SendError,
+    /// An HTTP probe got an answer, but not the expected status.
+    Status(u16),
+    /// The resolver answered that the name doesn't exist.
+    NxDomain,
+    /// The resolver answered with another error code, e.g. 2 for SERVFAIL.
+    DnsError(u8),
+}
+
+impl PingResult {
+    pub fn rtt(&self) -> Option<f64> {
+        match self {
+            PingResult::Reply(ms) => Some(*ms),
+            _ => None,
+        }
+    }
+
+    /// Short text for the sidebar.
+    pub fn label(&self) -> String {
+        match self {
+            PingResult::Empty => "-".to_string(),
+            PingResult::Reply(ms) => format!("{ms:.0} ms"),
+            PingResult::Timeout => "timeout".to_string(),
+            PingResult::Unreachable => "unreachable".to_string(),
+            PingResult::TtlExceeded => "TTL exceeded".to_string(),
+            PingResult::SendError => "ERR".to_string(),
+            PingResult::Status(code) => format!("HTTP {code}"),
+            PingResult::NxDomain => "NXDOMAIN".to_string(),
+            PingResult::DnsError(2) => "SERVFAIL".to_string(),
+            PingResult::DnsError(5) => "REFUSED".to_string(),
+            PingResult::DnsError(code) => format!("DNS error {code}"),
+        }
+    }
+}
+
+/// Recent results of one target.
+pub struct PingHistory {
+    pub target: PingTarget,
+    pub buffer: Arc<CircleVec<PingResult, 100>>,
+}
+
+/// Summary over a history, all times in ms. Everything but `loss` is `None` without replies.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PingStats {
+    /// % of sent pings that got no reply
+    pub loss: Option<f64>,
+    /// Interarrival jitter like RFC 3550 calculates it, from the differences between consecutive
+    /// round trip times.
+    pub jitter: Option<f64>,
+    pub p50: Option<f64>,
+    pub p95: Option<f64>,
+    pub max: Option<f64>,
+}
+
+impl PingStats {
+    pub fn of(results: &[PingResult]) -> Self {
+        let sent = results.iter().filter(|r| **r != PingResult::Empty).count();
+        let rtts = results.iter().filter_map(PingResult::rtt).collect_vec();
+        if sent == 0 {
+            return Default::default();
+        }
+
+        let jitter = (rtts.len() > 1).then(|| {
+            rtts.iter()
+                .tuple_windows()
+                .fold(0.0, |j, (a, b)| j + ((b - a).abs() - j) / 16.0)
+        });
+        let sorted = rtts
+            .iter()
+            .copied()
+            .sorted_by(|a, b| a.total_cmp(b))
+            .collect_vec();
+        // nearest rank
+        let percentile = |p: f64| {
+            let rank = ((p / 100.0 * sorted.len() as f64).ceil() as usize).max(1);
+            sorted.get(rank - 1).copied()
+        };
+
+        PingStats {
+            loss: Some((sent - rtts.len()) as f64 / sent as f64 * 100.0),
+            jitter,
+            p50: percentile(50.0),
+            p95: percentile(95.0),
+            max: sorted.last().copied(),
+        }
+    }
+}
+
+/// Keeps one pinging task per configured target. Targets can be added, changed and removed in
+/// the settings while running, a changed target starts over with an empty history.
+pub async fn ping_thread(
+    histories: Arc<Mutex<Vec<PingHistory>>>,
+    settings: Arc<Mutex<MySettings>>,
+) -> ! {
+    // the task of each history, in the same order
+    let mut tasks: Vec<JoinHandle<()>> = vec![];
+    loop {
+        let targets = settings.lock().current_settings.ping_targets.clone();
+        update_tasks(&mut histories.lock(), &mut tasks, targets);
+        sleep(std::time::Duration::from_secs(1)).await;
+    }
+}
+
+fn update_tasks(
+    histories: &mut Vec<PingHistory>,
+    tasks: &mut Vec<JoinHandle<()>>,
+    targets: Vec<PingTarget>,
+) {
+    if histories.iter().map(|h| &h.target).eq(targets.iter()) {
+        return;
+    }
+    let mut old = std::mem::take(histories)
+        .into_iter()
+        .zip(std::mem::take(tasks))
+        .collect_vec();
+    for target in targets {
+        let (history, task) = match old.iter().position(|(h, _)| h.target == target) {
+            Some(idx) => old.remove(idx),
+            None => {
+                let buffer = CircleVec::new();
+                let task = tokio::spawn(ping_target(target.clone(), buffer.clone()));
+                (PingHistory { target, buffer }, task)
+            }
+        };
+        histories.push(history);
+        tasks.push(task);
+    }
+    for (_, task) in old {
+        task.abort();
+    }
+}
+
+async fn ping_target(target: PingTarget, buffer: Arc<CircleVec<PingResult, 100>>) {
+    let interval = std::time::Duration::from_millis(target.interval_ms);
+    let timeout = std::time::Duration::from_millis(target.timeout_ms);
+    // no idle connections, every request has to connect like the first one
+    let client = reqwest::Client::builder()
+        .timeout(timeout)
+        .pool_max_idle_per_host(0)
+        .build()
+        .unwrap_or_default();
+    let mut address = None;
+    loop {
+        let start = Instant::now();
+        if address.is_none() && matches!(target.probe, Probe::Icmp | Probe::Tcp) {
+            address = resolve(&target.host).await;
+        }
+        let result = match (target.probe, address) {
+            (Probe::Http, _) => http_get(&client, &target).await,
+            (Probe::Dns, _) => dns_lookup(&target, timeout).await,
+            (_, None) => PingResult::SendError,
+            (Probe::Icmp, Some(ip)) => ping(ip, &target).await,
+            (Probe::Tcp, Some(ip)) => connect(SocketAddr::new(ip, target.port), timeout).await,
+        };
+        if result.rtt().is_none() {
+            // the host name might point somewhere else by now
+            address = None;
+        }
+        buffer.add(result);
+
+        sleep(interval.saturating_sub(start.elapsed())).await;
+    }
+}
+
+async fn resolve(host: &str) -> Option<IpAddr> {
+    if let Ok(ip) = host.parse() {
+        return Some(ip);
+    }
+    lookup_host((host, 0)).await.ok()?.next().map(|a| a.ip())
+}
+
+/// Ekko blocks until the reply or the timeout, keep it off the runtime.
+async fn ping(ip: IpAddr, target: &PingTarget) -> PingResult {
+    let hops = target.hops;
+    let timeout = std::time::Duration::from_millis(target.timeout_ms);
+    tokio::task::spawn_blocking(move || {
+        let Ok(ekko) = Ekko::with_target(ip) else {
+            return PingResult::SendError;
+        };
+        match ekko.send_with_settings(
+            hops,
+            EkkoSettings {
+                timeout,
+                ..Default::default()
+            },
+        ) {
+            Ok(EkkoResponse::Destination(res)) => {
+                PingResult::Reply(res.elapsed.as_secs_f64() * 1000.0)
+            }
+            Ok(EkkoResponse::Exceeded(_)) => PingResult::TtlExceeded,
+            Ok(EkkoResponse::Unreachable(_)) => PingResult::Unreachable,
+            // no answer, or nothing that belongs to this request, in time
+            Ok(_) => PingResult::Timeout,
+            Err(_) => PingResult::SendError,
+        }
+    })
+    .await
+    .unwrap_or(PingResult::SendError)
+}
+
+async fn connect(address: SocketAddr, timeout: std::time::Duration) -> PingResult {
+    let start = Instant::now();
+    match tokio::time::timeout(timeout, TcpStream::connect(address)).await {
+        Ok(Ok(_)) => PingResult::Reply(start.elapsed().as_secs_f64() * 1000.0),
+        // a refused connection still proves the host is up, but nothing listens on the port
+        Ok(Err(e)) if e.kind() == std::io::ErrorKind::ConnectionRefused => PingResult::Unreachable,
+        Ok(Err(_)) => PingResult::SendError,
+        Err(_) => PingResult::Timeout,
+    }
+}
+
+/// Time until the status line and headers arrive, the body isn't read.
+async fn http_get(client: &reqwest::Client, target: &PingTarget) -> PingResult {
+    let start = Instant::now();
+    match client.get(&target.host).send().await {
+        Ok(response) if response.status().as_u16() == target.expected_status => {
+            PingResult::Reply(start.elapsed().as_secs_f64() * 1000.0)
+        }
+        Ok(response) => PingResult::Status(response.status().as_u16()),
+        Err(e) if e.is_timeout() => PingResult::Timeout,
+        Err(e) if e.is_connect() => PingResult::Unreachable,
+        Err(_) => PingResult::SendError,
+    }
+}
+
+/// `host` is the resolver, with an optional port: `192.168.1.1` or `[::1]:5353`.
+async fn dns_lookup(target: &PingTarget, timeout: std::time::Duration) -> PingResult {
+    let resolver = match target.host.parse::<SocketAddr>() {
+        Ok(address) => address,
+        Err(_) => match resolve(&target.host).await {
+            Some(ip) => SocketAddr::new(ip, 53),
+            None => return PingResult::SendError,
+        },
+    };
+    let start = Instant::now();
+    match dns::lookup(resolver, &target.dns_name, target.dns_tcp, timeout).await {
+        Ok(DnsAnswer::NoError) => PingResult::Reply(start.elapsed().as_secs_f64() * 1000.0),
+        Ok(DnsAnswer::NxDomain) => PingResult::NxDomain,
+        Ok(DnsAnswer::Error(code)) => PingResult::DnsError(code),
+        Err(e) if e.kind() == std::io::ErrorKind::TimedOut => PingResult::Timeout,
+        Err(_) => PingResult::SendError,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+        runtime::Runtime,
+    };
+
+    const TIMEOUT: Duration = Duration::from_secs(2);
+
+    /// Answers one request with `status` and an empty body.
+    async fn http_server(status: &'static str) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 1024];
+            let _ = stream.read(&mut buf).await;
+            let response =
+                format!("HTTP/1.1 {status}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+            let _ = stream.write_all(response.as_bytes()).await;
+        });
+        address
+    }
+
+    /// A port nothing listens on anymore.
+    async fn closed_port() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        listener.local_addr().unwrap()
+    }
+
+    fn http_target(address: SocketAddr, path: &str) -> PingTarget {
+        PingTarget {
+            host: format!("http://{address}{path}"),
+            probe: Probe::Http,
+            ..Default::default()
+        }
+    }
+
+    fn client() -> reqwest::Client {
+        reqwest::Client::builder().timeout(TIMEOUT).build().unwrap()
+    }
+
+    #[test]
+    fn connect_to_a_listener() {
+        Runtime::new().unwrap().block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let result = connect(listener.local_addr().unwrap(), TIMEOUT).await;
+            assert!(
+                matches!(result, PingResult::Reply(ms) if ms >= 0.0),
+                "{result:?}"
+            );
+        });
+    }
+
+    #[test]
+    fn refused_connection_is_unreachable() {
+        Runtime::new().unwrap().block_on(async {
+            assert_eq!(
+                connect(closed_port().await, TIMEOUT).await,
+                PingResult::Unreachable
+            );
+            let target = http_target(closed_port().await, "/");
+            assert_eq!(http_get(&client(), &target).await, PingResult::Unreachable);
+        });
+    }
+
+    #[test]
+    fn http_get_checks_the_status() {
+        Runtime::new().unwrap().block_on(async {
+            let target = http_target(http_server("200 OK").await, "/");
+            let result = http_get(&client(), &target).await;
+            assert!(matches!(result, PingResult::Reply(_)), "{result:?}");
+
+            let target = http_target(http_server("404 Not Found").await, "/missing");
+            assert_eq!(http_get(&client(), &target).await, PingResult::Status(404));
+
+            let target = PingTarget {
+                expected_status: 404,
+                ..http_target(http_server("404 Not Found").await, "/missing")
+            };
+            let result = http_get(&client(), &target).await;
+            assert!(matches!(result, PingResult::Reply(_)), "{result:?}");
+        });
+    }
+
+    #[test]
+    fn stats_of_nothing_sent() {
+        assert_eq!(PingStats::of(&[]), PingStats::default());
+        assert_eq!(PingStats::of(&[PingResult::Empty; 5]), PingStats::default());
+    }
+
+    #[test]
+    fn stats_of_only_timeouts() {
+        let results = [
+            PingResult::Empty,
+            PingResult::Timeout,
+            PingResult::Timeout,
+            PingResult::Unreachable,
+        ];
+        assert_eq!(
+            PingStats::of(&results),
+            PingStats {
+                loss: Some(100.0),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn single_reply_has_no_jitter() {
+        let stats = PingStats::of(&[PingResult::Reply(12.5)]);
+        assert_eq!(
+            stats,
+            PingStats {
+                loss: Some(0.0),
+                jitter: None,
+                p50: Some(12.5),
+                p95: Some(12.5),
+                max: Some(12.5),
+            }
+        );
+    }
+
+    #[test]
+    fn percentiles_and_jitter() {
+        let mut results = vec![PingResult::Empty, PingResult::Timeout];
+        results.extend((1..=10).map(|i| PingResult::Reply(i as f64 * 10.0)));
+        results.push(PingResult::Timeout);
+        let stats = PingStats::of(&results);
+
+        assert_eq!(stats.loss, Some(2.0 / 12.0 * 100.0));
+        // nearest rank: the 5th and the 10th of 10
+        assert_eq!(stats.p50, Some(50.0));
+        assert_eq!(stats.p95, Some(100.0));
+        assert_eq!(stats.max, Some(100.0));
+        // 9 differences of 10 ms, each moving the estimate 1/16 closer
+        let jitter = 10.0 * (1.0 - (15.0f64 / 16.0).powi(9));
+        assert!((stats.jitter.unwrap() - jitter).abs() < 1e-9);
+    }
+
+    #[test]
+    fn percentiles_ignore_reply_order() {
+        let results = [80.0, 10.0, 30.0, 20.0].map(PingResult::Reply);
+        let stats = PingStats::of(&results);
+        assert_eq!(stats.p50, Some(20.0));
+        assert_eq!(stats.p95, Some(80.0));
+    }
+}
@@ -0,0 +1,273 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use itertools::Itertools;
+
+use crate::sources::{gpu::GpuProvider, pci_bus_id, GpuData};
+
+use super::hwmon::read_trimmed;
+
+const AMD_VENDOR_ID: &str = "0x1002";
+
+/// AMD GPUs driven by amdgpu, read from `/sys/class/drm/card*/device`.
+pub struct AmdGpuProvider {
+    root: PathBuf,
+}
+
+impl AmdGpuProvider {
+    pub fn new() -> Self {
+        Self::with_root("/sys/class/drm")
+    }
+
+    /// Read from a different directory than `/sys/class/drm`, e.g. a copied sysfs tree.
+    pub fn with_root(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+impl Default for AmdGpuProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GpuProvider for AmdGpuProvider {
+    fn read(&mut self) -> Vec<GpuData> {
+        find_cards(&self.root)
+            .iter()
+            .map(|device| read_card(device))
+            .collect_vec()
+    }
+}
+
+/// `device` directories of all AMD cards. Connector entries like `card0-DP-1` are skipped.
+pub fn find_cards(root: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(root) else {
+        return vec![];
+    };
+    entries
+        .filter_map(|e| e.ok())
+        .filter(|e| {
+            let name = e.file_name().to_string_lossy().to_string();
+            name.starts_with("card") && !name.contains('-')
+        })
+        .map(|e| e.path().join("device"))
+        .filter(|d| read_trimmed(&d.join("vendor")).as_deref() == Some(AMD_VENDOR_ID))
+        .filter(|d| d.join("gpu_busy_percent").exists())
+        .sorted()
+        .collect_vec()
+}
+
+pub fn read_card(device: &Path) -> GpuData {
+    let read_f32 = |path: &Path| read_trimmed(path)?.parse::<f32>().ok();
+    let hwmon = find_hwmon(device);
+    let hwmon_f32 = |file: &str| read_f32(&hwmon.as_ref()?.join(file));
+
+    let memory_used = read_f32(&device.join("mem_info_vram_used")).unwrap_or_default();
+    let memory_total = read_f32(&device.join("mem_info_vram_total")).unwrap_or_default();
+    let (clock_mhz, max_clock) = fs::read_to_string(device.join("pp_dpm_sclk"))
+        .map(|s| parse_dpm_clock(&s))
+        .unwrap_or_default();
+    let fan_percentage = match (hwmon_f32("pwm1"), hwmon_f32("pwm1_max")) {
+        (Some(pwm), Some(max)) if max > 0.0 => pwm / max * 100.0,
+        _ => 0.0,
+    };
+
+    GpuData {
+        name: read_trimmed(&device.join("product_name"))
+            .filter(|n| !n.is_empty())
+            .unwrap_or_else(|| default_name(device)),
+        bus_id: fs::read_to_string(device.join("uevent"))
+            .ok()
+            .and_then(|u| {
+                u.lines()
+                    .find_map(|l| l.strip_prefix("PCI_SLOT_NAME="))
+                    .map(pci_bus_id)
+            })
+            .unwrap_or_default(),
+        utilization: read_f32(&device.join("gpu_busy_percent")).unwrap_or_default() as f64,
+        temperature: hwmon_f32("temp1_input").unwrap_or_default() / 1000.0,
+        memory_free: memory_total - memory_used,
+        memory_used,
+        memory_total,
+        // power1_average was renamed to power1_input on newer kernels for some chips
+        power_usage: hwmon_f32("power1_average")
+            .or_else(|| hwmon_f32("power1_input"))
+            .unwrap_or_default()
+            / 1_000_000.0,
+        power_limit: hwmon_f32("power1_cap").unwrap_or_default() / 1_000_000.0,
+        fan_percentage,
+        clock_mhz,
+        max_clock,
+    }
+}
+
+/// Most cards have no `product_name`, the card keeps several of them apart: `AMD GPU (card1)`.
+fn default_name(device: &Path) -> String {
+    match device.parent().and_then(Path::file_name) {
+        Some(card) => format!("AMD GPU ({})", card.to_string_lossy()),
+        None => "AMD GPU".to_string(),
+    }
+}
+
+fn find_hwmon(device: &Path) -> Option<PathBuf> {
+    fs::read_dir(device.join("hwmon"))
+        .ok()?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .sorted()
+        .next()
+}
+
+/// (current MHz, max MHz) from `pp_dpm_sclk`, where the active level is marked with `*`:
+///
+/// ```text
+/// 0: 500Mhz
+/// 1: 1800Mhz *
+/// 2: 2450Mhz
+/// ```
+pub fn parse_dpm_clock(content: &str) -> (f32, f32) {
+    let levels = content
+        .lines()
+        .filter_map(|l| {
+            let (_, rest) = l.split_once(':')?;
+            let mhz = rest
+                .trim()
+                .trim_end_matches('*')
+                .trim()
+                .to_lowercase()
+                .trim_end_matches("mhz")
+                .parse::<f32>()
+                .ok()?;
+            Some((mhz, l.trim_end().ends_with('*')))
+        })
+        .collect_vec();
+    let current = levels
+        .iter()
+        .find(|(_, active)| *active)
+        .map(|(mhz, _)| *mhz)
+        .unwrap_or_default();
+    let max = levels.iter().map(|(mhz, _)| *mhz).fold(0.0, f32::max);
+    (current, max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(dir: &Path, file: &str, content: &str) {
+        fs::create_dir_all(dir).unwrap();
+        fs::write(dir.join(file), content).unwrap();
+    }
+
+    #[test]
+    fn cards_without_product_name_are_told_apart() {
+        let root = tempfile::tempdir().unwrap();
+        for card in ["card0", "card1"] {
+            let device = root.path().join(card).join("device");
+            write(&device, "vendor", "0x1002\n");
+            write(&device, "gpu_busy_percent", "3\n");
+            let slot = if card == "card0" { "03" } else { "0c" };
+            write(
+                &device,
+                "uevent",
+                &format!("DRIVER=amdgpu\nPCI_CLASS=30000\nPCI_SLOT_NAME=0000:{slot}:00.0\n"),
+            );
+        }
+        write(
+            &root.path().join("card1").join("device"),
+            "product_name",
+            "AMD Radeon RX 7800 XT\n",
+        );
+
+        let gpus = AmdGpuProvider::with_root(root.path()).read();
+        let names = gpus.iter().map(|g| g.name.as_str()).collect_vec();
+        assert_eq!(names, ["AMD GPU (card0)", "AMD Radeon RX 7800 XT"]);
+        let bus_ids = gpus.iter().map(|g| g.bus_id.as_str()).collect_vec();
+        assert_eq!(bus_ids, ["0000:03:00.0", "0000:0c:00.0"]);
+    }
+
+    #[test]
+    fn dpm_clock_active_and_max_level() {
+        assert_eq!(
+            parse_dpm_clock("0: 500Mhz\n1: 1800Mhz *\n2: 2450Mhz\n"),
+            (1800.0, 2450.0)
+        );
+        // the levels aren't always sorted, e.g. the deep sleep level on RDNA
+        assert_eq!(
+            parse_dpm_clock("S: 19Mhz *\n0: 500Mhz\n1: 2100MHz\n"),
+            (19.0, 2100.0)
+        );
+        assert_eq!(parse_dpm_clock("0: 500Mhz\n1: 800Mhz\n"), (0.0, 800.0));
+        assert_eq!(parse_dpm_clock(""), (0.0, 0.0));
+    }
+
+    /// A card with every file read, `hwmon` holds the hwmon files.
+    fn card(root: &Path, hwmon: &[(&str, &str)]) -> PathBuf {
+        let device = root.join("card0").join("device");
+        write(&device, "vendor", "0x1002\n");
+        write(&device, "gpu_busy_percent", "42\n");
+        write(&device, "mem_info_vram_used", "1073741824\n");
+        write(&device, "mem_info_vram_total", "17163091968\n");
+        write(
+            &device,
+            "pp_dpm_sclk",
+            "0: 500Mhz\n1: 2254Mhz *\n2: 2500Mhz\n",
+        );
+        let dir = device.join("hwmon").join("hwmon3");
+        for (file, content) in hwmon {
+            write(&dir, file, content);
+        }
+        device
+    }
+
+    #[test]
+    fn reads_a_card() {
+        let root = tempfile::tempdir().unwrap();
+        let device = card(
+            root.path(),
+            &[
+                ("temp1_input", "54000\n"),
+                ("power1_average", "187000000\n"),
+                ("power1_cap", "263000000\n"),
+                ("pwm1", "102\n"),
+                ("pwm1_max", "255\n"),
+            ],
+        );
+        let gpu = read_card(&device);
+        assert_eq!(gpu.utilization, 42.0);
+        assert_eq!(gpu.temperature, 54.0);
+        assert_eq!(gpu.memory_used, 1073741824.0);
+        assert_eq!(gpu.memory_total, 17163091968.0);
+        assert_eq!(gpu.memory_free, 17163091968.0 - 1073741824.0);
+        assert_eq!(gpu.power_usage, 187.0);
+        assert_eq!(gpu.power_limit, 263.0);
+        assert_eq!(gpu.fan_percentage, 40.0);
+        assert_eq!((gpu.clock_mhz, gpu.max_clock), (2254.0, 2500.0));
+    }
+
+    #[test]
+    fn power_falls_back_to_power1_input() {
+        let root = tempfile::tempdir().unwrap();
+        let device = card(root.path(), &[("power1_input", "95500000\n")]);
+        let gpu = read_card(&device);
+        assert_eq!(gpu.power_usage, 95.5);
+        // no other hwmon files, nothing to read
+        assert_eq!(gpu.temperature, 0.0);
+        assert_eq!(gpu.fan_percentage, 0.0);
+    }
+
+    #[test]
+    fn missing_files_read_as_zero() {
+        let root = tempfile::tempdir().unwrap();
+        let device = root.path().join("card0").join("device");
+        write(&device, "vendor", "0x1002\n");
+        let gpu = read_card(&device);
+        assert_eq!(gpu.name, "AMD GPU (card0)");
+        assert_eq!(gpu.memory_total, 0.0);
+        assert_eq!(gpu.power_usage, 0.0);
+        assert_eq!((gpu.clock_mhz, gpu.max_clock), (0.0, 0.0));
+    }
+}
@@ -0,0 +1,219 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use itertools::Itertools;
+use sysinfo::{DiskExt, System, SystemExt};
+
+use crate::sources::{DriveData, MetricSnapshot, MetricSource};
+
+/// `/proc/diskstats` counts sectors in 512 byte units regardless of the device sector size.
+const SECTOR_SIZE: f64 = 512.0;
+
+/// Busy time, throughput and IOPS per mounted block device from `/proc/diskstats`.
+pub struct DiskstatsSource {
+    path: PathBuf,
+    last: Option<(HashMap<String, DiskStat>, Instant)>,
+}
+
+impl DiskstatsSource {
+    pub fn new() -> Self {
+        Self::with_path("/proc/diskstats")
+    }
+
+    pub fn with_path(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            last: None,
+        }
+    }
+}
+
+impl Default for DiskstatsSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MetricSource for DiskstatsSource {
+    fn name(&self) -> &'static str {
+        "diskstats"
+    }
+
+    fn refresh(&mut self, system: &mut System) -> MetricSnapshot {
+        let Ok(content) = fs::read_to_string(&self.path) else {
+            return Default::default();
+        };
+        let now = Instant::now();
+        let stats = parse_diskstats(&content);
+
+        let drives = if let Some((last, last_time)) = &self.last {
+            let seconds = now.duration_since(*last_time).as_secs_f64();
+            system
+                .disks()
+                .iter()
+                .filter_map(|d| {
+                    let device = device_name(Path::new(d.name()))?;
+                    let rates =
+                        DiskRates::between(last.get(&device)?, stats.get(&device)?, seconds);
+                    Some(DriveData {
+                        mount: d.mount_point().to_string_lossy().to_string(),
+                        busy: Some(rates.busy),
+                        read_bytes_per_sec: Some(rates.read_bytes_per_sec),
+                        write_bytes_per_sec: Some(rates.write_bytes_per_sec),
+                        iops: Some(rates.iops),
+                        ..Default::default()
+                    })
+                })
+                .collect_vec()
+        } else {
+            vec![]
+        };
+        self.last = Some((stats, now));
+
+        MetricSnapshot {
+            drives: (!drives.is_empty()).then_some(drives),
+            ..Default::default()
+        }
+    }
+}
+
+/// Kernel name of the block device behind a mount source, `/dev/mapper/root` -> `dm-0`.
+fn device_name(source: &Path) -> Option<String> {
+    let resolved = fs::canonicalize(source).unwrap_or_else(|_| source.to_path_buf());
+    Some(resolved.file_name()?.to_string_lossy().to_string())
+}
+
+/// The cumulative counters of one line in `/proc/diskstats`.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiskStat {
+    pub reads_completed: u64,
+    pub sectors_read: u64,
+    pub writes_completed: u64,
+    pub sectors_written: u64,
+    /// ms the device had I/O in flight
+    pub io_ticks: u64,
+}
+
+/// Device name -> counters.
+pub fn parse_diskstats(content: &str) -> HashMap<String, DiskStat> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let fields = line.split_whitespace().collect_vec();
+            if fields.len() < 13 {
+                return None;
+            }
+            let field = |i: usize| fields[i].parse::<u64>().ok();
+            Some((
+                fields[2].to_string(),
+                DiskStat {
+                    reads_completed: field(3)?,
+                    sectors_read: field(5)?,
+                    writes_completed: field(7)?,
+                    sectors_written: field(9)?,
+                    io_ticks: field(12)?,
+                },
+            ))
+        })
+        .collect()
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub struct DiskRates {
+    /// %
+    pub busy: f64,
+    pub read_bytes_per_sec: f64,
+    pub write_bytes_per_sec: f64,
+    pub iops: f64,
+}
+
+impl DiskRates {
+    pub fn between(last: &DiskStat, current: &DiskStat, seconds: f64) -> Self {
+        if seconds <= 0.0 {
+            return Default::default();
+        }
+        let delta = |a: u64, b: u64| b.saturating_sub(a) as f64;
+        Self {
+            busy: (delta(last.io_ticks, current.io_ticks) / (seconds * 1000.0) * 100.0).min(100.0),
+            read_bytes_per_sec: delta(last.sectors_read, current.sectors_read) * SECTOR_SIZE
+                / seconds,
+            write_bytes_per_sec: delta(last.sectors_written, current.sectors_written) * SECTOR_SIZE
+                / seconds,
+            iops: (delta(last.reads_completed, current.reads_completed)
+                + delta(last.writes_completed, current.writes_completed))
+                / seconds,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DISKSTATS: &str =
+        "   8       0 sda 1000 10 20000 500 2000 20 40000 900 0 1500 1400 0 0 0 0
+   8       1 sda1 800 5 16000 400 1800 18 36000 800 0 1200 1200 0 0 0 0
+ 259       0 nvme0n1 5000 0 80000 700 3000 0 96000 600 0 2500 1300 0 0 0 0
+ 259       1 nvme0n1p1 4000 0 64000 600 2500 0 80000 500 0 2000 1100 0 0 0 0
+   7       0 loop0 12 0
+";
+
+    #[test]
+    fn partitions_keep_their_own_counters() {
+        let stats = parse_diskstats(DISKSTATS);
+        assert_eq!(stats.len(), 4, "short lines are skipped");
+        assert_eq!(
+            stats["sda"],
+            DiskStat {
+                reads_completed: 1000,
+                sectors_read: 20000,
+                writes_completed: 2000,
+                sectors_written: 40000,
+                io_ticks: 1500,
+            }
+        );
+        // a drive mounted from a partition must not get the whole disk's numbers
+        assert_eq!(stats["sda1"].sectors_read, 16000);
+        assert_eq!(stats["nvme0n1p1"].io_ticks, 2000);
+        assert_eq!(stats["nvme0n1"].io_ticks, 2500);
+        assert!(!stats.contains_key("loop0"));
+    }
+
+    #[test]
+    fn sectors_are_512_bytes() {
+        let last = DiskStat::default();
+        let current = DiskStat {
+            reads_completed: 30,
+            sectors_read: 2048,
+            writes_completed: 20,
+            sectors_written: 4096,
+            io_ticks: 250,
+        };
+        let rates = DiskRates::between(&last, &current, 0.5);
+        assert_eq!(rates.read_bytes_per_sec, 2048.0 * 512.0 / 0.5);
+        assert_eq!(rates.write_bytes_per_sec, 4096.0 * 512.0 / 0.5);
+        assert_eq!(rates.iops, 100.0);
+        assert_eq!(rates.busy, 50.0);
+    }
+
+    #[test]
+    fn counter_reset_reads_as_idle() {
+        let last = DiskStat {
+            reads_completed: 1000,
+            sectors_read: 20000,
+            writes_completed: 2000,
+            sectors_written: 40000,
+            io_ticks: 1500,
+        };
+        let rates = DiskRates::between(&last, &DiskStat::default(), 1.0);
+        assert_eq!(rates, DiskRates::default());
+        assert_eq!(
+            DiskRates::between(&DiskStat::default(), &last, 0.0),
+            DiskRates::default()
+        );
+    }
+}
@@ -0,0 +1,192 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use itertools::Itertools;
+use sysinfo::System;
+
+use crate::sources::{MetricSnapshot, MetricSource};
+
+/// Chips that report the CPU temperature.
+const CPU_CHIPS: [&str; 3] = ["coretemp", "k10temp", "zenpower"];
+
+/// CPU temperatures from `/sys/class/hwmon`.
+pub struct HwmonSource {
+    root: PathBuf,
+}
+
+impl HwmonSource {
+    pub fn new() -> Self {
+        Self::with_root("/sys/class/hwmon")
+    }
+
+    /// Read from a different directory than `/sys/class/hwmon`, e.g. a copied sysfs tree.
+    pub fn with_root(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+impl Default for HwmonSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MetricSource for HwmonSource {
+    fn name(&self) -> &'static str {
+        "hwmon"
+    }
+
+    fn refresh(&mut self, _system: &mut System) -> MetricSnapshot {
+        let temps = read_cpu_temps(&self.root);
+        MetricSnapshot {
+            core_temps: (!temps.cores.is_empty()).then_some(temps.cores),
+            cpu_temp: temps.package,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct CpuTemps {
+    /// °C, `Package id 0` for coretemp, `Tdie` or `Tctl` for AMD
+    pub package: Option<f32>,
+    /// (core index, °C), numbered across all sockets
+    pub cores: Vec<(i32, f32)>,
+}
+
+pub fn read_cpu_temps(root: &Path) -> CpuTemps {
+    let mut temps = CpuTemps::default();
+    let Ok(entries) = fs::read_dir(root) else {
+        return temps;
+    };
+
+    // hwmon numbering follows the socket order, keep it stable across refreshes
+    let chips = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| CPU_CHIPS.contains(&read_trimmed(&p.join("name")).unwrap_or_default().as_str()))
+        .sorted_by_key(|p| hwmon_index(p))
+        .collect_vec();
+
+    for chip in chips {
+        let sensors = read_chip_temps(&chip);
+        let core_offset = temps.cores.iter().map(|(i, _)| i + 1).max().unwrap_or(0);
+
+        let mut cores = sensors
+            .iter()
+            .filter_map(|(label, t)| {
+                let core = label.strip_prefix("Core ")?.trim().parse::<i32>().ok()?;
+                Some((core + core_offset, *t))
+            })
+            .collect_vec();
+        cores.sort_by_key(|(i, _)| *i);
+        temps.cores.extend(cores);
+
+        let package = ["Package id", "Tdie", "Tctl"].iter().find_map(|prefix| {
+            sensors
+                .iter()
+                .find(|(label, _)| label.starts_with(prefix))
+                .map(|(_, t)| *t)
+        });
+        temps.package = match (temps.package, package) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        };
+    }
+    temps
+}
+
+/// All `temp*_input` of one chip as (label, °C). Unlabeled inputs are called `temp<N>`.
+fn read_chip_temps(chip: &Path) -> Vec<(String, f32)> {
+    let Ok(entries) = fs::read_dir(chip) else {
+        return vec![];
+    };
+    entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let file_name = e.file_name().to_string_lossy().to_string();
+            let sensor = file_name.strip_suffix("_input")?;
+            if !sensor.starts_with("temp") {
+                return None;
+            }
+            let millidegrees = read_trimmed(&e.path())?.parse::<f32>().ok()?;
+            let label = read_trimmed(&chip.join(format!("{sensor}_label")))
+                .unwrap_or_else(|| sensor.to_string());
+            Some((label, millidegrees / 1000.0))
+        })
+        .sorted_by(|a, b| a.0.cmp(&b.0))
+        .collect_vec()
+}
+
+fn hwmon_index(path: &Path) -> u32 {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .and_then(|n| n.strip_prefix("hwmon"))
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(u32::MAX)
+}
+
+pub(crate) fn read_trimmed(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sysinfo::SystemExt;
+
+    fn write(dir: &Path, file: &str, content: &str) {
+        fs::create_dir_all(dir).unwrap();
+        fs::write(dir.join(file), content).unwrap();
+    }
+
+    #[test]
+    fn reads_coretemp_and_skips_other_chips() {
+        let root = tempfile::tempdir().unwrap();
+        let coretemp = root.path().join("hwmon1");
+        write(&coretemp, "name", "coretemp\n");
+        write(&coretemp, "temp1_input", "48000\n");
+        write(&coretemp, "temp1_label", "Package id 0\n");
+        write(&coretemp, "temp2_input", "45000\n");
+        write(&coretemp, "temp2_label", "Core 0\n");
+        write(&coretemp, "temp3_input", "47000\n");
+        write(&coretemp, "temp3_label", "Core 1\n");
+        let nvme = root.path().join("hwmon0");
+        write(&nvme, "name", "nvme\n");
+        write(&nvme, "temp1_input", "38850\n");
+        // a chip that lost its name file is skipped, not guessed
+        let unnamed = root.path().join("hwmon2");
+        write(&unnamed, "temp1_input", "99000\n");
+
+        let temps = read_cpu_temps(root.path());
+        assert_eq!(temps.package, Some(48.0));
+        assert_eq!(temps.cores, vec![(0, 45.0), (1, 47.0)]);
+    }
+
+    #[test]
+    fn unlabeled_inputs_are_named_after_the_file() {
+        let root = tempfile::tempdir().unwrap();
+        let k10temp = root.path().join("hwmon0");
+        write(&k10temp, "name", "k10temp\n");
+        write(&k10temp, "temp1_input", "52875\n");
+        write(&k10temp, "temp3_input", "49250\n");
+        write(&k10temp, "temp3_label", "Tccd1\n");
+
+        assert_eq!(
+            read_chip_temps(&k10temp),
+            vec![("Tccd1".to_string(), 49.25), ("temp1".to_string(), 52.875)]
+        );
+        // without a Tctl label there is no package temperature
+        let snapshot = HwmonSource::with_root(root.path()).refresh(&mut System::new());
+        assert_eq!(snapshot.cpu_temp, None);
+        assert_eq!(snapshot.core_temps, None);
+    }
+
+    #[test]
+    fn missing_root_reads_nothing() {
+        let temps = read_cpu_temps(Path::new("/nonexistent/hwmon"));
+        assert_eq!(temps, CpuTemps::default());
+    }
+}
@@ -0,0 +1,66 @@
+use std::{process::Command, sync::Arc};
+
+use parking_lot::Mutex;
+
+use crate::{
+    ohw::OHWNode,
+    settings::MySettings,
+    sources::{
+        gpu::{nvidia_provider, GpuSource},
+        lhm::LhmSource,
+        system::SysinfoSource,
+        MetricSource,
+    },
+    MyApp,
+};
+
+mod amdgpu;
+mod diskstats;
+mod hwmon;
+mod power_supply;
+mod proc;
+mod rapl;
+
+/// hwmon and RAPL cover what LHM would provide.
+pub const LHM_REQUIRED: bool = false;
+
+pub const BIN_NAME: &str = "ststat";
+
+pub fn default_sources(ohw_info: Arc<Mutex<Option<OHWNode>>>) -> Vec<Box<dyn MetricSource>> {
+    vec![
+        Box::new(LhmSource::new(ohw_info)),
+        Box::new(hwmon::HwmonSource::new()),
+        Box::new(rapl::RaplSource::new()),
+        Box::new(diskstats::DiskstatsSource::new()),
+        Box::new(proc::ProcSource::new()),
+        Box::new(power_supply::PowerSupplySource::new()),
+        Box::new(GpuSource::new(vec![
+            Box::new(amdgpu::AmdGpuProvider::new()),
+            // no LHM for the live values
+            nvidia_provider(true),
+        ])),
+        Box::new(SysinfoSource),
+    ]
+}
+
+/// There is no appbar equivalent that works across window managers, the sidebar is a plain
+/// undecorated window on Linux.
+pub fn setup_sidebar(_appdata: &MyApp, _scale_override: Option<f32>) {}
+
+pub fn dispose_sidebar(_settings: Arc<Mutex<MySettings>>) {}
+
+pub fn accent_color() -> Option<[u8; 4]> {
+    None
+}
+
+pub fn open_task_manager() {
+    match Command::new("gnome-system-monitor").spawn() {
+        Ok(_c) => println!("Starting System Monitor"),
+        Err(e) => println!("{e}"),
+    };
+}
+
+/// Started from a terminal, that's where errors go.
+pub fn show_error(text: &str) {
+    println!("{text}");
+}
@@ -0,0 +1,233 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use itertools::Itertools;
+use sysinfo::System;
+
+use crate::sources::{
+    BatteryCapacity, BatteryData, BatteryStatus, CapacityUnit, MetricSnapshot, MetricSource,
+};
+
+use super::hwmon::read_trimmed;
+
+/// The first system battery in `/sys/class/power_supply`.
+pub struct PowerSupplySource {
+    root: PathBuf,
+}
+
+impl PowerSupplySource {
+    pub fn new() -> Self {
+        Self::with_root("/sys/class/power_supply")
+    }
+
+    /// Read from a different directory than `/sys/class/power_supply`, e.g. a copied sysfs tree.
+    pub fn with_root(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+impl Default for PowerSupplySource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MetricSource for PowerSupplySource {
+    fn name(&self) -> &'static str {
+        "power_supply"
+    }
+
+    fn refresh(&mut self, _system: &mut System) -> MetricSnapshot {
+        MetricSnapshot {
+            battery: find_batteries(&self.root)
+                .iter()
+                .find_map(|b| read_battery(b)),
+            ..Default::default()
+        }
+    }
+}
+
+/// Supplies of type `Battery`, without peripherals like mice (`scope` = `Device`).
+pub fn find_batteries(root: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(root) else {
+        return vec![];
+    };
+    entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| read_trimmed(&p.join("type")).as_deref() == Some("Battery"))
+        .filter(|p| read_trimmed(&p.join("scope")).as_deref() != Some("Device"))
+        .sorted()
+        .collect_vec()
+}
+
+/// Drivers report either charge (`charge_*`, `current_now` in µAh / µA) or energy
+/// (`energy_*`, `power_now` in µWh / µW), some report both.
+pub fn read_battery(dir: &Path) -> Option<BatteryData> {
+    let read_f64 = |file: &str| read_trimmed(&dir.join(file))?.parse::<f64>().ok();
+
+    let status = read_trimmed(&dir.join("status"))
+        .map(|s| parse_status(&s))
+        .unwrap_or_default();
+    let level = read_f64("capacity")
+        .or_else(|| Some(read_f64("energy_now")? / read_f64("energy_full")? * 100.0))
+        .or_else(|| Some(read_f64("charge_now")? / read_f64("charge_full")? * 100.0))?;
+
+    let voltage = read_f64("voltage_now").map(|v| v / 1_000_000.0);
+    let power = read_f64("power_now").map(|p| p / 1_000_000.0);
+    let current = read_f64("current_now")
+        .map(|c| c / 1_000_000.0)
+        .or_else(|| Some(power? / voltage.filter(|v| *v > 0.0)?));
+
+    // most drivers report the absolute value, the direction is in `status`
+    let sign = if status == BatteryStatus::Discharging {
+        -1.0
+    } else {
+        1.0
+    };
+
+    let capacity = match (read_f64("energy_full"), read_f64("energy_full_design")) {
+        (Some(full), Some(design)) => Some(BatteryCapacity {
+            full: full / 1_000_000.0,
+            design: design / 1_000_000.0,
+            unit: CapacityUnit::Wh,
+        }),
+        _ => match (read_f64("charge_full"), read_f64("charge_full_design")) {
+            (Some(full), Some(design)) => Some(BatteryCapacity {
+                full: full / 1_000_000.0,
+                design: design / 1_000_000.0,
+                unit: CapacityUnit::Ah,
+            }),
+            _ => None,
+        },
+    };
+
+    Some(BatteryData {
+        level,
+        charge_rate: current.map(|c| sign * c.abs()).unwrap_or_default(),
+        power: power.map(|p| sign * p.abs()),
+        status,
+        capacity,
+    })
+}
+
+pub fn parse_status(status: &str) -> BatteryStatus {
+    match status {
+        "Charging" => BatteryStatus::Charging,
+        "Discharging" => BatteryStatus::Discharging,
+        "Not charging" => BatteryStatus::NotCharging,
+        "Full" => BatteryStatus::Full,
+        _ => BatteryStatus::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sysinfo::SystemExt;
+
+    fn write(dir: &Path, file: &str, content: &str) {
+        fs::create_dir_all(dir).unwrap();
+        fs::write(dir.join(file), content).unwrap();
+    }
+
+    fn ac(root: &Path) {
+        let ac = root.join("AC");
+        write(&ac, "type", "Mains\n");
+        write(&ac, "online", "1\n");
+    }
+
+    #[test]
+    fn energy_battery_reports_watt_hours() {
+        let root = tempfile::tempdir().unwrap();
+        ac(root.path());
+        let bat = root.path().join("BAT0");
+        write(&bat, "type", "Battery\n");
+        write(&bat, "status", "Discharging\n");
+        write(&bat, "capacity", "80\n");
+        write(&bat, "energy_now", "36000000\n");
+        write(&bat, "energy_full", "45000000\n");
+        write(&bat, "energy_full_design", "50000000\n");
+        write(&bat, "power_now", "12000000\n");
+        write(&bat, "voltage_now", "12000000\n");
+
+        let battery = PowerSupplySource::with_root(root.path())
+            .refresh(&mut System::new())
+            .battery
+            .unwrap();
+        assert_eq!(battery.level, 80.0);
+        assert_eq!(battery.status, BatteryStatus::Discharging);
+        assert_eq!(battery.power, Some(-12.0));
+        // no current_now, derived from power and voltage
+        assert_eq!(battery.charge_rate, -1.0);
+        assert_eq!(
+            battery.capacity,
+            Some(BatteryCapacity {
+                full: 45.0,
+                design: 50.0,
+                unit: CapacityUnit::Wh,
+            })
+        );
+    }
+
+    #[test]
+    fn charge_battery_reports_amp_hours() {
+        let root = tempfile::tempdir().unwrap();
+        let bat = root.path().join("BAT1");
+        write(&bat, "type", "Battery\n");
+        write(&bat, "status", "Charging\n");
+        // no capacity file, the level comes from the charge
+        write(&bat, "charge_now", "1500000\n");
+        write(&bat, "charge_full", "3000000\n");
+        write(&bat, "charge_full_design", "4000000\n");
+        write(&bat, "current_now", "500000\n");
+        write(&bat, "voltage_now", "11400000\n");
+        write(&bat, "power_now", "5700000\n");
+
+        let battery = read_battery(&bat).unwrap();
+        assert_eq!(battery.level, 50.0);
+        assert_eq!(battery.status, BatteryStatus::Charging);
+        assert_eq!(battery.charge_rate, 0.5);
+        assert_eq!(
+            battery.capacity,
+            Some(BatteryCapacity {
+                full: 3.0,
+                design: 4.0,
+                unit: CapacityUnit::Ah,
+            })
+        );
+    }
+
+    #[test]
+    fn missing_power_now_leaves_power_empty() {
+        let root = tempfile::tempdir().unwrap();
+        let bat = root.path().join("BAT0");
+        write(&bat, "type", "Battery\n");
+        write(&bat, "status", "Full\n");
+        write(&bat, "capacity", "100\n");
+
+        let battery = read_battery(&bat).unwrap();
+        assert_eq!(battery.status, BatteryStatus::Full);
+        assert_eq!(battery.power, None);
+        assert_eq!(battery.charge_rate, 0.0);
+        assert_eq!(battery.capacity, None);
+    }
+
+    #[test]
+    fn ac_only_and_peripherals_have_no_battery() {
+        let root = tempfile::tempdir().unwrap();
+        ac(root.path());
+        let mouse = root.path().join("hidpp_battery_0");
+        write(&mouse, "type", "Battery\n");
+        write(&mouse, "scope", "Device\n");
+        write(&mouse, "capacity", "60\n");
+
+        assert!(find_batteries(root.path()).is_empty());
+        assert!(PowerSupplySource::with_root(root.path())
+            .refresh(&mut System::new())
+            .battery
+            .is_none());
+    }
+}
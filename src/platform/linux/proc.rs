@@ -0,0 +1,293 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use itertools::Itertools;
+use sysinfo::System;
+
+use crate::{
+    process::Process,
+    sources::{MetricSnapshot, MetricSource},
+};
+
+/// The process list from `/proc/[pid]/stat` and `/proc/[pid]/status`, cpu usage relative to the
+/// time `/proc/stat` counted since the last refresh.
+pub struct ProcSource {
+    root: PathBuf,
+    /// pid -> utime + stime of the last refresh
+    last_jiffies: HashMap<u32, u64>,
+    /// Jiffies all cpus together spent in any state at the last refresh, from `/proc/stat`
+    last_total: Option<u64>,
+}
+
+impl ProcSource {
+    pub fn new() -> Self {
+        Self::with_root("/proc")
+    }
+
+    pub fn with_root(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            last_jiffies: HashMap::new(),
+            last_total: None,
+        }
+    }
+}
+
+impl Default for ProcSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MetricSource for ProcSource {
+    fn name(&self) -> &'static str {
+        "proc"
+    }
+
+    fn refresh(&mut self, _system: &mut System) -> MetricSnapshot {
+        let Ok(entries) = fs::read_dir(&self.root) else {
+            return Default::default();
+        };
+        let cpu_total = fs::read_to_string(self.root.join("stat"))
+            .ok()
+            .and_then(|s| parse_cpu_total(&s));
+        // elapsed time in jiffies of a single cpu, so 100% is one busy core like sysinfo
+        let elapsed = match (cpu_total, self.last_total) {
+            (Some((total, cpus)), Some(last)) => total.saturating_sub(last) as f64 / cpus as f64,
+            _ => 0.0,
+        };
+
+        let mut jiffies = HashMap::new();
+        let mut processes = vec![];
+        for pid in entries
+            .filter_map(|e| e.ok())
+            .filter_map(|e| e.file_name().to_str()?.parse::<u32>().ok())
+        {
+            let Some(p) = read_process(&self.root.join(pid.to_string())) else {
+                continue;
+            };
+            // processes started since the last refresh count from zero
+            let cpu = match self.last_jiffies.get(&pid) {
+                Some(last) if elapsed > 0.0 => {
+                    p.jiffies.saturating_sub(*last) as f64 / elapsed * 100.0
+                }
+                _ => 0.0,
+            };
+            jiffies.insert(pid, p.jiffies);
+            processes.push(Process {
+                name: p.comm,
+                cpu,
+                memory: p.rss_bytes,
+                count: 1,
+            });
+        }
+        self.last_jiffies = jiffies;
+        self.last_total = cpu_total.map(|(total, _)| total);
+
+        MetricSnapshot {
+            processes: Some(group_by_name(processes)),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct ProcStat {
+    pub comm: String,
+    /// utime + stime
+    pub jiffies: u64,
+    pub rss_bytes: i64,
+}
+
+fn read_process(dir: &Path) -> Option<ProcStat> {
+    let (comm, jiffies) = parse_stat(&fs::read_to_string(dir.join("stat")).ok()?)?;
+    // kernel threads have no VmRSS
+    let rss_bytes = fs::read_to_string(dir.join("status"))
+        .ok()
+        .and_then(|s| parse_vm_rss(&s))
+        .unwrap_or_default();
+    Some(ProcStat {
+        comm,
+        jiffies,
+        rss_bytes,
+    })
+}
+
+/// (comm, utime + stime) from the contents of `/proc/[pid]/stat`.
+///
+/// comm may contain spaces and parentheses itself, so it ends at the last `)`.
+pub fn parse_stat(content: &str) -> Option<(String, u64)> {
+    let start = content.find('(')?;
+    let end = content.rfind(')')?;
+    let comm = content.get(start + 1..end)?.to_string();
+    // fields after comm start with state (3), utime and stime are 14 and 15
+    let fields = content.get(end + 1..)?.split_whitespace().collect_vec();
+    let utime = fields.get(11)?.parse::<u64>().ok()?;
+    let stime = fields.get(12)?.parse::<u64>().ok()?;
+    Some((comm, utime + stime))
+}
+
+/// (jiffies of all cpus, number of cpus) from the contents of `/proc/stat`. The total adds up
+/// every state of the `cpu` line, the count is the number of `cpuN` lines.
+pub fn parse_cpu_total(content: &str) -> Option<(u64, usize)> {
+    let total = content
+        .lines()
+        .find_map(|l| l.strip_prefix("cpu "))?
+        .split_whitespace()
+        .filter_map(|v| v.parse::<u64>().ok())
+        .sum();
+    let cpus = content
+        .lines()
+        .filter(|l| l.starts_with("cpu") && !l.starts_with("cpu "))
+        .count();
+    (cpus > 0).then_some((total, cpus))
+}
+
+/// `VmRSS` from the contents of `/proc/[pid]/status`, in bytes.
+pub fn parse_vm_rss(content: &str) -> Option<i64> {
+    let line = content.lines().find(|l| l.starts_with("VmRSS:"))?;
+    let kb = line
+        .trim_start_matches("VmRSS:")
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse::<i64>()
+        .ok()?;
+    Some(kb * 1024)
+}
+
+pub fn group_by_name(processes: Vec<Process>) -> Vec<Process> {
+    processes
+        .into_iter()
+        .sorted_unstable_by_key(|p| p.name.clone())
+        .group_by(|p| p.name.clone())
+        .into_iter()
+        .map(|(_name, group)| group.reduce(|acc, p| acc + p).unwrap())
+        .collect_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use sysinfo::SystemExt;
+
+    use super::*;
+
+    #[test]
+    fn parse_stat_reads_cpu_ticks() {
+        let stat = "1234 (bash) S 1 1234 1234 34816 1234 4194304 2000 0 3 0 250 50 0 0 20 0 1 0 \
+                    100 9000000 800 18446744073709551615 1 1 0 0 0 0 65536 3670020 1266777851 0 0 \
+                    0 17 2 0 0 0 0 0";
+        assert_eq!(parse_stat(stat), Some(("bash".to_string(), 300)));
+    }
+
+    #[test]
+    fn parse_stat_handles_spaces_and_parens_in_the_name() {
+        let stat = "4321 (Web Content) S 1 4321 4321 0 -1 4194560 100 0 0 0 70 30 0 0 20 0 1 0 5 \
+                    0 0";
+        assert_eq!(parse_stat(stat), Some(("Web Content".to_string(), 100)));
+        // a name may contain ") S 1", only the last ')' ends it
+        let stat = "99 (evil) S 1 2) R 1 99 99 0 -1 4194560 100 0 0 0 11 22 0 0 20 0 1 0 5 0 0";
+        assert_eq!(parse_stat(stat), Some(("evil) S 1 2".to_string(), 33)));
+    }
+
+    #[test]
+    fn parse_stat_rejects_truncated_lines() {
+        assert_eq!(parse_stat("1 (init) S 0 1"), None);
+        assert_eq!(parse_stat(""), None);
+    }
+
+    #[test]
+    fn parse_vm_rss_is_in_bytes() {
+        let status = "Name:\tbash\nVmPeak:\t  10000 kB\nVmRSS:\t    5120 kB\nThreads:\t1\n";
+        assert_eq!(parse_vm_rss(status), Some(5120 * 1024));
+        // kernel threads have no VmRSS line
+        assert_eq!(parse_vm_rss("Name:\tkthreadd\nThreads:\t1\n"), None);
+    }
+
+    #[test]
+    fn parse_cpu_total_adds_up_all_states() {
+        let stat = "cpu  100 5 50 1000 10 0 5 0 0 0\ncpu0 50 2 25 500 5 0 3 0 0 0\n\
+                    cpu1 50 3 25 500 5 0 2 0 0 0\nintr 12345\nctxt 678\n";
+        assert_eq!(parse_cpu_total(stat), Some((1170, 2)));
+        assert_eq!(parse_cpu_total("intr 12345\n"), None);
+    }
+
+    fn write(dir: &Path, file: &str, content: &str) {
+        fs::create_dir_all(dir).unwrap();
+        fs::write(dir.join(file), content).unwrap();
+    }
+
+    /// A `/proc/[pid]` with the given utime and stime.
+    fn process(root: &Path, pid: u32, comm: &str, utime: u64, stime: u64, rss_kb: i64) {
+        let dir = root.join(pid.to_string());
+        write(
+            &dir,
+            "stat",
+            &format!("{pid} ({comm}) S 1 {pid} {pid} 0 -1 4194560 100 0 0 0 {utime} {stime} 0 0 20 0 1 0 5 0 0"),
+        );
+        write(
+            &dir,
+            "status",
+            &format!("Name:\t{comm}\nVmRSS:\t{rss_kb} kB\n"),
+        );
+    }
+
+    /// A `/proc/stat` of a 4 cpu system.
+    fn cpu_total(root: &Path, total: u64) {
+        let cpus = (0..4)
+            .map(|i| format!("cpu{i} {} 0 0 0\n", total / 4))
+            .join("");
+        write(root, "stat", &format!("cpu  {total} 0 0 0\n{cpus}intr 0\n"));
+    }
+
+    fn by_name(snapshot: MetricSnapshot) -> HashMap<String, Process> {
+        snapshot
+            .processes
+            .unwrap()
+            .into_iter()
+            .map(|p| (p.name.clone(), p))
+            .collect()
+    }
+
+    #[test]
+    fn refresh_measures_cpu_between_refreshes() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path();
+        let mut system = System::new();
+        cpu_total(root, 40_000);
+        process(root, 1, "init", 10, 5, 4096);
+        process(root, 100, "worker", 200, 100, 1024);
+        process(root, 101, "worker", 0, 0, 1024);
+        process(root, 200, "short lived", 1, 1, 512);
+        let mut source = ProcSource::with_root(root);
+
+        let first = by_name(source.refresh(&mut system));
+        assert_eq!(first.len(), 3);
+        // nothing to compare against yet
+        assert!(first.values().all(|p| p.cpu == 0.0));
+        assert_eq!(first["worker"].count, 2);
+        assert_eq!(first["worker"].memory, 2048 * 1024);
+
+        // 4 cpus for 200 jiffies each
+        cpu_total(root, 40_800);
+        process(root, 1, "init", 12, 6, 4096);
+        process(root, 100, "worker", 300, 200, 1024);
+        process(root, 101, "worker", 40, 10, 1024);
+        fs::remove_dir_all(root.join("200")).unwrap();
+        // started since the last refresh
+        process(root, 300, "new", 500, 0, 256);
+
+        let second = by_name(source.refresh(&mut system));
+        assert_eq!(
+            second.keys().sorted().collect_vec(),
+            ["init", "new", "worker"]
+        );
+        assert_eq!(second["init"].cpu, 1.5);
+        // 200 + 50 jiffies across two processes
+        assert_eq!(second["worker"].cpu, 125.0);
+        assert_eq!(second["new"].cpu, 0.0);
+    }
+}
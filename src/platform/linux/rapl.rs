@@ -0,0 +1,188 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use itertools::Itertools;
+use sysinfo::System;
+
+use crate::sources::{MetricSnapshot, MetricSource};
+
+use super::hwmon::read_trimmed;
+
+/// CPU package power from the RAPL energy counters in `/sys/class/powercap`.
+///
+/// `energy_uj` is only readable by root on most distributions, the source stays silent otherwise.
+pub struct RaplSource {
+    root: PathBuf,
+    /// zone name -> (energy_uj, read at)
+    last: HashMap<String, (u64, Instant)>,
+}
+
+impl RaplSource {
+    pub fn new() -> Self {
+        Self::with_root("/sys/class/powercap")
+    }
+
+    /// Read from a different directory than `/sys/class/powercap`, e.g. a copied sysfs tree.
+    pub fn with_root(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            last: HashMap::new(),
+        }
+    }
+}
+
+impl Default for RaplSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MetricSource for RaplSource {
+    fn name(&self) -> &'static str {
+        "rapl"
+    }
+
+    fn refresh(&mut self, _system: &mut System) -> MetricSnapshot {
+        let zones = read_package_zones(&self.root);
+        if zones.is_empty() {
+            return Default::default();
+        }
+
+        let now = Instant::now();
+        let mut power = None;
+        for zone in &zones {
+            if let Some((last_energy, last_time)) = self.last.get(&zone.name) {
+                let seconds = now.duration_since(*last_time).as_secs_f64();
+                if seconds > 0.0 {
+                    let delta =
+                        energy_delta(*last_energy, zone.energy_uj, zone.max_energy_range_uj);
+                    *power.get_or_insert(0.0) += delta as f64 / 1_000_000.0 / seconds;
+                }
+            }
+            self.last.insert(zone.name.clone(), (zone.energy_uj, now));
+        }
+
+        let limits = zones.iter().filter_map(|z| z.power_limit_uw).collect_vec();
+        let power_limit = (!limits.is_empty())
+            .then(|| limits.iter().sum::<u64>() as f64 / 1_000_000.0)
+            .filter(|l| *l > 0.0);
+
+        MetricSnapshot {
+            cpu_power: power,
+            cpu_power_limit: power_limit,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct RaplZone {
+    /// e.g. `package-0`
+    pub name: String,
+    pub energy_uj: u64,
+    pub max_energy_range_uj: u64,
+    /// long term limit if the zone names its constraints, otherwise `constraint_0`
+    pub power_limit_uw: Option<u64>,
+}
+
+/// The top level `intel-rapl:N` zones, one per socket. Subzones like `intel-rapl:0:0` (core,
+/// uncore, dram) are already included in their package and skipped.
+pub fn read_package_zones(root: &Path) -> Vec<RaplZone> {
+    let Ok(entries) = fs::read_dir(root) else {
+        return vec![];
+    };
+    entries
+        .filter_map(|e| e.ok())
+        .filter(|e| {
+            let name = e.file_name().to_string_lossy().to_string();
+            name.starts_with("intel-rapl:") && name.matches(':').count() == 1
+        })
+        .sorted_by_key(|e| e.file_name())
+        .filter_map(|e| read_zone(&e.path()))
+        .collect_vec()
+}
+
+fn read_zone(path: &Path) -> Option<RaplZone> {
+    let read_u64 = |file: &str| read_trimmed(&path.join(file))?.parse::<u64>().ok();
+    Some(RaplZone {
+        name: read_trimmed(&path.join("name"))
+            .unwrap_or_else(|| path.to_string_lossy().to_string()),
+        energy_uj: read_u64("energy_uj")?,
+        max_energy_range_uj: read_u64("max_energy_range_uj").unwrap_or(u64::MAX),
+        power_limit_uw: read_power_limit(path),
+    })
+}
+
+fn read_power_limit(path: &Path) -> Option<u64> {
+    let limit = |i: usize| {
+        read_trimmed(&path.join(format!("constraint_{i}_power_limit_uw")))?
+            .parse::<u64>()
+            .ok()
+    };
+    let long_term = (0..3).find(|i| {
+        read_trimmed(&path.join(format!("constraint_{i}_name"))).as_deref() == Some("long_term")
+    });
+    limit(long_term.unwrap_or(0))
+}
+
+/// The counter restarts at zero after reaching `max_energy_range_uj`.
+pub fn energy_delta(last: u64, current: u64, max_energy_range_uj: u64) -> u64 {
+    if current >= last {
+        current - last
+    } else {
+        max_energy_range_uj
+            .saturating_sub(last)
+            .saturating_add(current)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    const MAX_RANGE: u64 = 262_143_328_850;
+
+    fn write_zone(root: &Path, dir: &str, energy_uj: u64) {
+        let zone = root.join(dir);
+        fs::create_dir_all(&zone).unwrap();
+        fs::write(zone.join("name"), "package-0\n").unwrap();
+        fs::write(zone.join("energy_uj"), format!("{energy_uj}\n")).unwrap();
+        fs::write(zone.join("max_energy_range_uj"), format!("{MAX_RANGE}\n")).unwrap();
+        fs::write(zone.join("constraint_0_name"), "long_term\n").unwrap();
+        fs::write(zone.join("constraint_0_power_limit_uw"), "65000000\n").unwrap();
+    }
+
+    #[test]
+    fn energy_delta_wraps_at_max_range() {
+        assert_eq!(energy_delta(1_000, 4_000, MAX_RANGE), 3_000);
+        assert_eq!(
+            energy_delta(MAX_RANGE - 328_850, 500_000, MAX_RANGE),
+            828_850
+        );
+    }
+
+    #[test]
+    fn power_survives_a_counter_wrap() {
+        let root = tempfile::tempdir().unwrap();
+        write_zone(root.path(), "intel-rapl:0", MAX_RANGE - 328_850);
+        // subzones are part of the package already
+        write_zone(root.path(), "intel-rapl:0:0", 5);
+
+        let mut source = RaplSource::with_root(root.path());
+        let mut system = System::default();
+        let first = source.refresh(&mut system);
+        assert_eq!(first.cpu_power, None);
+        assert_eq!(first.cpu_power_limit, Some(65.0));
+
+        std::thread::sleep(Duration::from_millis(50));
+        write_zone(root.path(), "intel-rapl:0", 500_000);
+        let power = source.refresh(&mut system).cpu_power.unwrap();
+        // 828850 µJ in at least 50ms, not a negative or huge jump
+        assert!(power > 0.0 && power <= 0.828_85 / 0.05, "{power} W");
+    }
+}
@@ -0,0 +1,12 @@
+//! Everything that talks to the OS directly. Each platform module provides the same set of
+//! functions, the rest of STStat only uses what is re-exported here.
+
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(windows)]
+mod windows;
+
+#[cfg(target_os = "linux")]
+pub use self::linux::*;
+#[cfg(windows)]
+pub use self::windows::*;
@@ -0,0 +1,74 @@
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use tokio::process::Command;
+use windows::{
+    core::PCWSTR,
+    Win32::{
+        Foundation::{BOOL, HWND},
+        Graphics::Dwm::DwmGetColorizationColor,
+        UI::WindowsAndMessaging::{MessageBoxW, MB_ICONERROR, MB_OK},
+    },
+};
+
+use crate::{
+    ohw::OHWNode,
+    sources::{
+        gpu::{nvidia_provider, GpuSource},
+        lhm::LhmSource,
+        system::SysinfoSource,
+        MetricSource,
+    },
+};
+
+mod autostart;
+mod pdh;
+mod sidebar;
+
+pub use sidebar::{dispose_sidebar, setup_sidebar};
+
+/// Temperatures and power only come from LHM.
+pub const LHM_REQUIRED: bool = true;
+
+pub const BIN_NAME: &str = "ststat.exe";
+
+pub fn default_sources(ohw_info: Arc<Mutex<Option<OHWNode>>>) -> Vec<Box<dyn MetricSource>> {
+    vec![
+        Box::new(LhmSource::new(ohw_info)),
+        Box::new(GpuSource::new(vec![nvidia_provider(false)])),
+        Box::new(pdh::PdhSource::open()),
+        Box::new(SysinfoSource),
+    ]
+}
+
+/// DWM colorization color as `[a, r, g, b]`.
+pub fn accent_color() -> Option<[u8; 4]> {
+    let mut col: u32 = 0;
+    let mut opaque: BOOL = BOOL(0);
+    unsafe { DwmGetColorizationColor(&mut col, &mut opaque) }.ok()?;
+    Some(col.to_be_bytes())
+}
+
+pub fn open_task_manager() {
+    match Command::new("powershell")
+        .args(["start", "taskmgr", "-v runAs"])
+        .spawn()
+    {
+        Ok(_c) => println!("Starting Task Manager"),
+        Err(e) => println!("{e}"),
+    };
+}
+
+/// Release builds have no console, `println!` would go nowhere.
+pub fn show_error(text: &str) {
+    let wide = |s: &str| s.encode_utf16().chain([0]).collect::<Vec<_>>();
+    let (text, caption) = (wide(text), wide("STStat"));
+    unsafe {
+        MessageBoxW(
+            HWND(0),
+            PCWSTR::from_raw(text.as_ptr()),
+            PCWSTR::from_raw(caption.as_ptr()),
+            MB_OK | MB_ICONERROR,
+        )
+    };
+}
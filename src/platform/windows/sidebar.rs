@@ -55,7 +55,7 @@ lazy_static! {
 //     return_hwnd: Option<HWND>,
 // }
 
-pub(crate) fn setup_sidebar(appdata: &MyApp, scale_override: Option<f32>) {
+pub fn setup_sidebar(appdata: &MyApp, scale_override: Option<f32>) {
     // find handle: enum active windows, find window with my process id
     // let pid = std::process::id();
 
@@ -0,0 +1,256 @@
+use std::{fmt::Display, str::FromStr};
+
+/// The base unit a [`Quantity`] is stored in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Unit {
+    /// A plain number without unit
+    #[default]
+    None,
+    Celsius,
+    Percent,
+    Hertz,
+    Watt,
+    Volt,
+    Ampere,
+    Rpm,
+    Byte,
+    BytePerSecond,
+    WattHour,
+    AmpereHour,
+}
+
+impl Unit {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Unit::None => "",
+            Unit::Celsius => "°C",
+            Unit::Percent => "%",
+            Unit::Hertz => "Hz",
+            Unit::Watt => "W",
+            Unit::Volt => "V",
+            Unit::Ampere => "A",
+            Unit::Rpm => "RPM",
+            Unit::Byte => "B",
+            Unit::BytePerSecond => "B/s",
+            Unit::WattHour => "Wh",
+            Unit::AmpereHour => "Ah",
+        }
+    }
+}
+
+/// Base unit and factor for the raw values of an LHM `SensorType`.
+pub fn sensor_type_unit(sensor_type: &str) -> Option<(Unit, f64)> {
+    Some(match sensor_type {
+        "Voltage" => (Unit::Volt, 1.0),
+        "Current" => (Unit::Ampere, 1.0),
+        "Power" => (Unit::Watt, 1.0),
+        "Clock" => (Unit::Hertz, 1e6),
+        "Frequency" => (Unit::Hertz, 1.0),
+        "Temperature" => (Unit::Celsius, 1.0),
+        "Load" | "Control" | "Level" | "Humidity" => (Unit::Percent, 1.0),
+        "Fan" => (Unit::Rpm, 1.0),
+        "Data" => (Unit::Byte, 1024.0 * 1024.0 * 1024.0),
+        "SmallData" => (Unit::Byte, 1024.0 * 1024.0),
+        "Throughput" => (Unit::BytePerSecond, 1.0),
+        "Energy" => (Unit::WattHour, 1e-3),
+        "Factor" => (Unit::None, 1.0),
+        _ => return None,
+    })
+}
+
+/// Every unit suffix LHM prints, with the factor to its base unit. Sizes are binary, like LHM
+/// calculates them.
+const SUFFIXES: [(&str, Unit, f64); 30] = [
+    ("", Unit::None, 1.0),
+    ("°C", Unit::Celsius, 1.0),
+    ("%", Unit::Percent, 1.0),
+    ("Hz", Unit::Hertz, 1.0),
+    ("kHz", Unit::Hertz, 1e3),
+    ("MHz", Unit::Hertz, 1e6),
+    ("GHz", Unit::Hertz, 1e9),
+    ("mW", Unit::Watt, 1e-3),
+    ("W", Unit::Watt, 1.0),
+    ("mV", Unit::Volt, 1e-3),
+    ("V", Unit::Volt, 1.0),
+    ("mA", Unit::Ampere, 1e-3),
+    ("A", Unit::Ampere, 1.0),
+    ("RPM", Unit::Rpm, 1.0),
+    ("B", Unit::Byte, 1.0),
+    ("KB", Unit::Byte, 1024.0),
+    ("MB", Unit::Byte, 1024.0 * 1024.0),
+    ("GB", Unit::Byte, 1024.0 * 1024.0 * 1024.0),
+    ("TB", Unit::Byte, 1024.0 * 1024.0 * 1024.0 * 1024.0),
+    ("B/s", Unit::BytePerSecond, 1.0),
+    ("KB/s", Unit::BytePerSecond, 1024.0),
+    ("MB/s", Unit::BytePerSecond, 1024.0 * 1024.0),
+    ("GB/s", Unit::BytePerSecond, 1024.0 * 1024.0 * 1024.0),
+    ("mWh", Unit::WattHour, 1e-3),
+    ("Wh", Unit::WattHour, 1.0),
+    ("kWh", Unit::WattHour, 1e3),
+    ("mAh", Unit::AmpereHour, 1e-3),
+    ("Ah", Unit::AmpereHour, 1.0),
+    ("rpm", Unit::Rpm, 1.0),
+    ("°", Unit::Celsius, 1.0),
+];
+
+/// A sensor value converted to its base unit, e.g. `"1.5 GB"` -> 1610612736 [`Unit::Byte`].
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Quantity {
+    pub value: f64,
+    pub unit: Unit,
+}
+
+impl Quantity {
+    /// The value in another unit of the same kind, e.g. `MHz` for a clock. `None` if `symbol`
+    /// doesn't measure the same thing.
+    pub fn value_in(&self, symbol: &str) -> Option<f64> {
+        SUFFIXES
+            .iter()
+            .find(|(s, unit, _)| *s == symbol && *unit == self.unit)
+            .map(|(_, _, factor)| self.value / factor)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QuantityError {
+    Empty,
+    InvalidNumber(String),
+    UnknownUnit(String),
+}
+
+impl Display for QuantityError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QuantityError::Empty => write!(f, "empty value"),
+            QuantityError::InvalidNumber(n) => write!(f, "'{n}' is not a number"),
+            QuantityError::UnknownUnit(u) => write!(f, "unknown unit '{u}'"),
+        }
+    }
+}
+
+impl std::error::Error for QuantityError {}
+
+impl FromStr for Quantity {
+    type Err = QuantityError;
+
+    /// Accepts LHM's formatting in any locale, `"45,0 °C"` as well as `"45.0 °C"`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err(QuantityError::Empty);
+        }
+        let split = s
+            .find(|c: char| !(c.is_ascii_digit() || matches!(c, '.' | ',' | '-' | '+')))
+            .unwrap_or(s.len());
+        let (number, suffix) = s.split_at(split);
+        let suffix = suffix.trim();
+
+        let value = number
+            .trim()
+            .replace(',', ".")
+            .parse::<f64>()
+            .map_err(|_| QuantityError::InvalidNumber(number.trim().to_string()))?;
+        let (_, unit, factor) = SUFFIXES
+            .iter()
+            .find(|(symbol, _, _)| *symbol == suffix)
+            .ok_or_else(|| QuantityError::UnknownUnit(suffix.to_string()))?;
+
+        Ok(Quantity {
+            value: value * factor,
+            unit: *unit,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn q(s: &str) -> Quantity {
+        s.parse().unwrap()
+    }
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() <= b.abs() * 1e-12, "{a} != {b}");
+    }
+
+    #[test]
+    fn scales_suffixes_to_the_base_unit() {
+        let rate = q("1,5 KB/s");
+        assert_eq!(rate.unit, Unit::BytePerSecond);
+        assert_close(rate.value, 1536.0);
+        let size = q("3 MB");
+        assert_eq!(size.unit, Unit::Byte);
+        assert_close(size.value, 3.0 * 1024.0 * 1024.0);
+        let energy = q("1200 mWh");
+        assert_eq!(energy.unit, Unit::WattHour);
+        assert_close(energy.value, 1.2);
+        let clock = q("4.2 GHz");
+        assert_eq!(clock.unit, Unit::Hertz);
+        assert_close(clock.value, 4.2e9);
+    }
+
+    #[test]
+    fn every_suffix_round_trips() {
+        for (symbol, unit, factor) in SUFFIXES {
+            let parsed = q(&format!("2 {symbol}"));
+            assert_eq!(parsed.unit, unit, "{symbol}");
+            assert_close(parsed.value, 2.0 * factor);
+            assert_close(parsed.value_in(symbol).unwrap(), 2.0);
+        }
+    }
+
+    #[test]
+    fn decimal_comma_and_sign() {
+        assert_eq!(q("45,0 °C"), q("45.0 °C"));
+        assert_eq!(q(" -12,25 V ").value, -12.25);
+        assert_eq!(
+            q("7"),
+            Quantity {
+                value: 7.0,
+                unit: Unit::None
+            }
+        );
+    }
+
+    #[test]
+    fn converts_within_a_dimension_only() {
+        let clock = q("4200 MHz");
+        assert_close(clock.value_in("GHz").unwrap(), 4.2);
+        assert_close(clock.value_in("kHz").unwrap(), 4.2e6);
+        assert_close(q("2 GB").value_in("MB").unwrap(), 2048.0);
+        assert_eq!(clock.value_in("MB"), None);
+        assert_eq!(clock.value_in("°C"), None);
+        assert_eq!(q("2 GB").value_in("GB/s"), None);
+        assert_eq!(clock.value_in("furlong"), None);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!("".parse::<Quantity>(), Err(QuantityError::Empty));
+        assert_eq!("   ".parse::<Quantity>(), Err(QuantityError::Empty));
+        assert_eq!(
+            "1,234.5 MHz".parse::<Quantity>(),
+            Err(QuantityError::InvalidNumber("1,234.5".to_string()))
+        );
+        assert_eq!(
+            "n/a".parse::<Quantity>(),
+            Err(QuantityError::InvalidNumber(String::new()))
+        );
+        assert_eq!(
+            "12 furlongs".parse::<Quantity>(),
+            Err(QuantityError::UnknownUnit("furlongs".to_string()))
+        );
+    }
+
+    #[test]
+    fn sensor_types() {
+        assert_eq!(sensor_type_unit("Clock"), Some((Unit::Hertz, 1e6)));
+        assert_eq!(
+            sensor_type_unit("SmallData"),
+            Some((Unit::Byte, 1024.0 * 1024.0))
+        );
+        assert_eq!(sensor_type_unit("Energy"), Some((Unit::WattHour, 1e-3)));
+        assert_eq!(sensor_type_unit("Noise"), None);
+    }
+}
@@ -0,0 +1,227 @@
+//! Sessions are gzipped JSON lines, one [`Frame`] per refresh. Frames carry no timestamp,
+//! replay shows one frame per refresh interval, scaled by `--speed`. Traffic and usage in a
+//! frame are per refresh, so skipping or repeating frames to match the wall clock would
+//! distort them.
+
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use chrono::Local;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use sysinfo::System;
+
+use crate::{
+    ohw::OHWNode,
+    sources::{MetricSnapshot, MetricSource},
+};
+
+#[derive(Serialize, Deserialize)]
+struct Frame {
+    ohw: Option<OHWNode>,
+    /// Everything all sources provided, merged.
+    snapshot: MetricSnapshot,
+}
+
+pub struct Recorder {
+    path: PathBuf,
+    writer: GzEncoder<BufWriter<File>>,
+    frames: u64,
+}
+
+impl Recorder {
+    /// Starts a new `ststat-<timestamp>.rec.gz` in the working directory.
+    pub fn create() -> std::io::Result<Self> {
+        let path = PathBuf::from(format!(
+            "ststat-{}.rec.gz",
+            Local::now().format("%Y%m%d-%H%M%S")
+        ));
+        println!("Recording to {}", path.display());
+        Self::create_at(path)
+    }
+
+    fn create_at(path: PathBuf) -> std::io::Result<Self> {
+        let file = File::create(&path)?;
+        Ok(Self {
+            path,
+            writer: GzEncoder::new(BufWriter::new(file), Compression::default()),
+            frames: 0,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn write(
+        &mut self,
+        ohw: &Option<OHWNode>,
+        snapshot: &MetricSnapshot,
+    ) -> std::io::Result<()> {
+        let frame = Frame {
+            ohw: ohw.clone(),
+            snapshot: snapshot.clone(),
+        };
+        serde_json::to_writer(&mut self.writer, &frame)?;
+        self.writer.write_all(b"\n")?;
+        self.frames += 1;
+        // the app is usually ended with Ctrl+C or a kill, keep what's there readable
+        if self.frames.is_multiple_of(10) {
+            self.writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        if let Err(e) = self.writer.try_finish() {
+            println!("Recording {}: {e}", self.path.display());
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReplayArgs {
+    pub path: PathBuf,
+    /// 1.0 is one frame per refresh interval
+    pub speed: f64,
+}
+
+/// Plays a recording back in place of every other source, one frame per refresh.
+pub struct ReplaySource {
+    lines: std::io::Lines<BufReader<GzDecoder<File>>>,
+    ohw_info: Arc<Mutex<Option<OHWNode>>>,
+    finished: bool,
+}
+
+impl ReplaySource {
+    pub fn open(path: &Path, ohw_info: Arc<Mutex<Option<OHWNode>>>) -> std::io::Result<Self> {
+        let file = File::open(path)?;
+        Ok(Self {
+            lines: BufReader::new(GzDecoder::new(file)).lines(),
+            ohw_info,
+            finished: false,
+        })
+    }
+
+    fn next_frame(&mut self) -> Option<Frame> {
+        // a recording that was cut off ends in a broken line, that's just the end
+        let line = self.lines.next()?.ok()?;
+        match serde_json::from_str(&line) {
+            Ok(frame) => Some(frame),
+            Err(e) => {
+                println!("Replay: {e}");
+                None
+            }
+        }
+    }
+}
+
+impl MetricSource for ReplaySource {
+    fn name(&self) -> &'static str {
+        "replay"
+    }
+
+    fn refresh(&mut self, _system: &mut System) -> MetricSnapshot {
+        if self.finished {
+            return Default::default();
+        }
+        let Some(frame) = self.next_frame() else {
+            println!("Replay finished");
+            self.finished = true;
+            return Default::default();
+        };
+        *self.ohw_info.lock() = frame.ohw;
+        frame.snapshot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sysinfo::SystemExt;
+
+    use super::*;
+
+    fn snapshot(cpu_usage: f32) -> MetricSnapshot {
+        MetricSnapshot {
+            cpu_usage: Some(cpu_usage),
+            ..Default::default()
+        }
+    }
+
+    fn record(path: &Path, frames: usize) {
+        let mut recorder = Recorder::create_at(path.to_path_buf()).unwrap();
+        for i in 0..frames {
+            let ohw = OHWNode {
+                Text: format!("frame {i}"),
+                ..Default::default()
+            };
+            recorder.write(&Some(ohw), &snapshot(i as f32)).unwrap();
+        }
+    }
+
+    /// The cpu usage of every frame until the replay ends.
+    fn replay(path: &Path) -> Vec<f32> {
+        let ohw_info = Arc::new(Mutex::new(None));
+        let mut source = ReplaySource::open(path, ohw_info.clone()).unwrap();
+        let mut system = System::new();
+        let mut played = vec![];
+        while let Some(cpu_usage) = source.refresh(&mut system).cpu_usage {
+            let text = format!("frame {}", played.len());
+            assert_eq!(ohw_info.lock().as_ref().unwrap().Text, text);
+            played.push(cpu_usage);
+        }
+        assert!(source.refresh(&mut system).cpu_usage.is_none());
+        played
+    }
+
+    #[test]
+    fn replays_what_was_recorded() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.rec.gz");
+        record(&path, 25);
+        assert_eq!(replay(&path), (0..25).map(|i| i as f32).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn truncated_recording_ends_early() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.rec.gz");
+        record(&path, 500);
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &bytes[..bytes.len() / 2]).unwrap();
+
+        let played = replay(&path);
+        assert!(played.len() < 500);
+        assert_eq!(
+            played,
+            (0..played.len()).map(|i| i as f32).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn reads_frames_with_a_timestamp() {
+        // recordings made before frames dropped their `time`
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("old.rec.gz");
+        let mut frame = serde_json::to_value(Frame {
+            ohw: Some(OHWNode {
+                Text: "frame 0".to_string(),
+                ..Default::default()
+            }),
+            snapshot: snapshot(3.0),
+        })
+        .unwrap();
+        frame["time"] = serde_json::json!(1_700_000_000_000i64);
+        let mut writer = GzEncoder::new(File::create(&path).unwrap(), Compression::default());
+        writeln!(writer, "{frame}").unwrap();
+        writer.finish().unwrap();
+        assert_eq!(replay(&path), [3.0]);
+    }
+}
@@ -0,0 +1,114 @@
+use std::sync::Arc;
+
+use eframe::egui::{CollapsingHeader, Grid, RichText, TextEdit, Ui};
+use parking_lot::Mutex;
+
+use crate::ohw::OHWNode;
+
+/// Renders the last LHM tree, so selector paths can be looked up without reading data.json.
+pub fn show_sensor_browser(
+    ui: &mut Ui,
+    ohw_info: &Arc<Mutex<Option<OHWNode>>>,
+    filter: &mut String,
+) {
+    ui.add(TextEdit::singleline(filter).hint_text("search"));
+    let ohw = ohw_info.lock();
+    let Some(root) = ohw.as_ref() else {
+        ui.label("No data from LibreHardwareMonitor.");
+        return;
+    };
+    let filter = filter.to_lowercase();
+    for (i, n) in root.Children.iter().enumerate() {
+        show_node(ui, n, format!("#{i}"), &filter, !filter.is_empty());
+    }
+}
+
+/// `searching` stays set below a match, where `filter` is cleared.
+fn show_node(ui: &mut Ui, n: &OHWNode, path: String, filter: &str, searching: bool) {
+    if !filter.is_empty() && !matches_filter(n, &path, filter) {
+        return;
+    }
+    // a match shows everything below it
+    let filter = if matches_self(n, &path, filter) {
+        ""
+    } else {
+        filter
+    };
+
+    if n.Children.is_empty() {
+        show_sensors(ui, &path, vec![(n, path.clone())]);
+        return;
+    }
+
+    // Keep everything that matches visible while searching.
+    CollapsingHeader::new(&n.Text)
+        .id_source(&path)
+        .open(searching.then_some(true))
+        .show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(RichText::new(&path).small().weak());
+                copy_button(ui, &path);
+            });
+            let (sensors, groups): (Vec<_>, Vec<_>) = (0..n.Children.len())
+                .map(|i| (&n.Children[i], child_path(n, i, &path)))
+                .filter(|(c, p)| filter.is_empty() || matches_filter(c, p, filter))
+                .partition(|(c, _)| c.Children.is_empty());
+            if !sensors.is_empty() {
+                show_sensors(ui, &path, sensors);
+            }
+            for (c, p) in groups {
+                show_node(ui, c, p, filter, searching);
+            }
+        });
+}
+
+/// One row per sensor, with its range next to the value.
+fn show_sensors(ui: &mut Ui, id: &str, sensors: Vec<(&OHWNode, String)>) {
+    Grid::new(("sensors", id)).striped(true).show(ui, |ui| {
+        ui.label("");
+        for header in ["value", "min", "max"] {
+            ui.label(RichText::new(header).small().weak());
+        }
+        ui.end_row();
+        for (n, path) in sensors {
+            ui.label(&n.Text).on_hover_text(&path);
+            ui.label(&n.Value);
+            ui.label(RichText::new(&n.Min).weak());
+            ui.label(RichText::new(&n.Max).weak());
+            copy_button(ui, &path);
+            ui.end_row();
+        }
+    });
+}
+
+fn copy_button(ui: &mut Ui, path: &str) {
+    if ui.small_button("📋").on_hover_text("copy path").clicked() {
+        ui.output_mut(|o| o.copied_text = path.to_string());
+    }
+}
+
+/// The most stable selector for a child: its SensorId, else its exact Text, else its index if a
+/// sibling has the same Text.
+fn child_path(parent: &OHWNode, i: usize, parent_path: &str) -> String {
+    let c = &parent.Children[i];
+    if let Some(id) = c.SensorId.as_ref().or(c.HardwareId.as_ref()) {
+        return format!("@{id}");
+    }
+    let unique = parent.Children.iter().filter(|s| s.Text == c.Text).count() == 1;
+    let segment = if unique {
+        format!("={}", c.Text.replace('|', r"\|"))
+    } else {
+        format!("#{i}")
+    };
+    format!("{parent_path}|{segment}")
+}
+
+fn matches_self(n: &OHWNode, path: &str, filter: &str) -> bool {
+    n.Text.to_lowercase().contains(filter) || path.to_lowercase().contains(filter)
+}
+
+fn matches_filter(n: &OHWNode, path: &str, filter: &str) -> bool {
+    matches_self(n, path, filter)
+        || (0..n.Children.len())
+            .any(|i| matches_filter(&n.Children[i], &child_path(n, i, path), filter))
+}
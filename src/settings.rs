@@ -1,16 +1,19 @@
 use std::{
     collections::HashMap,
+    fmt::Display,
     fs::{self, File},
     io::BufWriter,
+    ops::RangeInclusive,
 };
 
 use display_info::DisplayInfo;
-use eframe::egui::{DragValue, Ui};
+use eframe::egui::{ComboBox, DragValue, RichText, TextEdit, Ui};
 use serde::{Deserialize, Serialize};
-use sysinfo::SystemExt;
 
 use crate::{
-    sidebar::{dispose_sidebar, setup_sidebar},
+    platform::{dispose_sidebar, setup_sidebar},
+    recording::Recorder,
+    sensor_browser::show_sensor_browser,
     CurrentStep, MyApp, SIDEBAR_WIDTH,
 };
 
@@ -20,7 +23,7 @@ pub struct MySettings {
     pub current_settings: InnerSettings,
 }
 
-#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Clone)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
 #[serde(default)]
 pub struct InnerSettings {
     pub networks: HashMap<String, bool>,
@@ -30,6 +33,203 @@ pub struct InnerSettings {
     pub track_timings: bool,
     pub max_cpu_power: f64,
     pub use_plain_dark_background: bool,
+    pub lhm: LhmSettings,
+    pub prometheus: PrometheusSettings,
+    pub tiles: Vec<TileSettings>,
+    pub ping_targets: Vec<PingTarget>,
+}
+
+impl Default for InnerSettings {
+    fn default() -> Self {
+        Self {
+            networks: Default::default(),
+            display_right: false,
+            screen_id: 0,
+            location: Default::default(),
+            track_timings: false,
+            max_cpu_power: 0.0,
+            use_plain_dark_background: false,
+            lhm: Default::default(),
+            prometheus: Default::default(),
+            tiles: vec![],
+            ping_targets: vec![PingTarget::default()],
+        }
+    }
+}
+
+/// A host the ping section keeps an eye on.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[serde(default)]
+pub struct PingTarget {
+    pub label: String,
+    /// IP address or host name, the whole URL for `Http`
+    pub host: String,
+    pub interval_ms: u64,
+    pub timeout_ms: u64,
+    pub probe: Probe,
+    /// Maximum number of hops (TTL) an ICMP request may take.
+    pub hops: u32,
+    /// `Tcp` only
+    pub port: u16,
+    /// `Http` only, any other status counts as a failure.
+    pub expected_status: u16,
+    /// `Dns` only, the name to look up
+    pub dns_name: String,
+    /// `Dns` only, query over TCP instead of UDP
+    pub dns_tcp: bool,
+}
+
+/// What the ping settings accept, in ms. Anything faster would keep a task busy.
+const PING_INTERVAL_MS: RangeInclusive<u64> = 200..=60000;
+const PING_TIMEOUT_MS: RangeInclusive<u64> = 100..=10000;
+
+impl PingTarget {
+    /// Puts values edited into conf.json back into the ranges the settings allow.
+    pub fn validate(&mut self) {
+        self.interval_ms = self
+            .interval_ms
+            .clamp(*PING_INTERVAL_MS.start(), *PING_INTERVAL_MS.end());
+        self.timeout_ms = self
+            .timeout_ms
+            .clamp(*PING_TIMEOUT_MS.start(), *PING_TIMEOUT_MS.end());
+    }
+}
+
+/// How a target is pinged. Not every network lets ICMP through.
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+pub enum Probe {
+    #[default]
+    Icmp,
+    /// Time to connect to `port`
+    Tcp,
+    /// Time until the response headers of a GET arrive, over a new connection
+    Http,
+    /// Time for `host`, a DNS resolver, to look up `dns_name`
+    Dns,
+}
+
+impl Display for Probe {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Probe::Icmp => write!(f, "ICMP"),
+            Probe::Tcp => write!(f, "TCP"),
+            Probe::Http => write!(f, "HTTP"),
+            Probe::Dns => write!(f, "DNS"),
+        }
+    }
+}
+
+impl Default for PingTarget {
+    fn default() -> Self {
+        Self {
+            label: "Google".to_string(),
+            host: "8.8.8.8".to_string(),
+            interval_ms: 1000,
+            timeout_ms: 950,
+            probe: Probe::Icmp,
+            hops: 32,
+            port: 443,
+            expected_status: 200,
+            dns_name: "example.com".to_string(),
+            dns_tcp: false,
+        }
+    }
+}
+
+/// A user defined sensor readout, only configurable in conf.json.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[serde(default)]
+pub struct TileSettings {
+    pub label: String,
+    /// An LHM selector like `@/lpc/nct6798d/fan/1`, or `metric:cpu_power` for a value every
+    /// platform provides.
+    pub source: String,
+    /// Unit to convert to before display, e.g. `MHz`. Empty shows the sensor's base unit.
+    pub unit: String,
+    /// Range of the bar and graph, in `unit`.
+    pub min: f64,
+    pub max: f64,
+    /// Empty picks a color by position.
+    pub color: Option<[u8; 3]>,
+    pub style: TileStyle,
+}
+
+impl Default for TileSettings {
+    fn default() -> Self {
+        Self {
+            label: String::new(),
+            source: String::new(),
+            unit: String::new(),
+            min: 0.0,
+            max: 100.0,
+            color: None,
+            style: Default::default(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+pub enum TileStyle {
+    #[default]
+    Bar,
+    Graph,
+    Text,
+}
+
+/// Where to find the LibreHardwareMonitor web server.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[serde(default)]
+pub struct LhmSettings {
+    /// `http` or `https`
+    pub scheme: String,
+    pub host: String,
+    pub port: u16,
+    /// Basic auth is only sent if a username is set.
+    pub username: String,
+    pub password: String,
+    pub timeout_ms: u64,
+    /// Read the tree from lm-sensors' `sensors -j` instead of the web server.
+    pub lm_sensors: bool,
+}
+
+impl Default for LhmSettings {
+    fn default() -> Self {
+        Self {
+            scheme: "http".to_string(),
+            host: "localhost".to_string(),
+            port: 8085,
+            username: String::new(),
+            password: String::new(),
+            timeout_ms: 950,
+            lm_sensors: false,
+        }
+    }
+}
+
+impl LhmSettings {
+    pub fn url(&self) -> String {
+        format!("{}://{}:{}/data.json", self.scheme, self.host, self.port)
+    }
+}
+
+/// A node_exporter to read CPU, memory, temperatures, network and drives from, in place of the
+/// local values.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[serde(default)]
+pub struct PrometheusSettings {
+    pub enabled: bool,
+    pub url: String,
+    pub timeout_ms: u64,
+}
+
+impl Default for PrometheusSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            url: "http://localhost:9100/metrics".to_string(),
+            timeout_ms: 950,
+        }
+    }
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, PartialEq, Clone)]
@@ -42,9 +242,10 @@ pub struct Location {
 
 impl MySettings {
     pub fn load() -> Self {
-        let inner: InnerSettings =
+        let mut inner: InnerSettings =
             serde_json::from_str(&fs::read_to_string("conf.json").unwrap_or_default())
                 .unwrap_or_default();
+        inner.ping_targets.iter_mut().for_each(PingTarget::validate);
         let s = Self {
             current_settings: inner.clone(),
             loaded_settings: inner,
@@ -77,13 +278,13 @@ pub fn show_settings(appdata: &mut MyApp, ui: &mut Ui, scale_override: Option<f3
     if appdata.show_settings {
         ui.separator();
         ui.label("Show Networks:");
-        for (net, _) in appdata.system_status.networks() {
+        for (net, _) in &appdata.networks {
             let e = settings
                 .current_settings
                 .networks
                 .entry(net.to_string())
                 .or_insert(false);
-            ui.checkbox(e, net);
+            ui.checkbox(e, net.as_str());
         }
         ui.separator();
         ui.label("Screen ID:");
@@ -98,6 +299,29 @@ pub fn show_settings(appdata: &mut MyApp, ui: &mut Ui, scale_override: Option<f3
             "Use plain dark background color",
         );
         ui.separator();
+        show_lhm_settings(&mut settings.current_settings.lhm, ui);
+        ui.collapsing("Sensor browser", |ui| {
+            show_sensor_browser(ui, &appdata.ohw_info, &mut appdata.sensor_filter)
+        });
+        ui.separator();
+        show_prometheus_settings(&mut settings.current_settings.prometheus, ui);
+        ui.separator();
+        show_ping_settings(&mut settings.current_settings.ping_targets, ui);
+        ui.separator();
+        let mut recording = appdata.recorder.is_some();
+        if ui.checkbox(&mut recording, "record session").changed() {
+            appdata.recorder = if recording {
+                Recorder::create()
+                    .map_err(|e| println!("Recording: {e}"))
+                    .ok()
+            } else {
+                None
+            };
+        }
+        if let Some(recorder) = &appdata.recorder {
+            ui.label(RichText::new(recorder.path().display().to_string()).small());
+        }
+        ui.separator();
         ui.checkbox(&mut settings.current_settings.track_timings, "trace perf");
         if ui.button("save trace").clicked() {
             use std::io::prelude::*;
@@ -114,6 +338,146 @@ pub fn show_settings(appdata: &mut MyApp, ui: &mut Ui, scale_override: Option<f3
     drop(settings);
 }
 
+fn show_lhm_settings(lhm: &mut LhmSettings, ui: &mut Ui) {
+    ui.label("LibreHardwareMonitor:");
+    if cfg!(target_os = "linux") {
+        ui.checkbox(&mut lhm.lm_sensors, "use lm-sensors instead");
+    }
+    ComboBox::from_id_source("lhm_scheme")
+        .selected_text(lhm.scheme.clone())
+        .show_ui(ui, |ui| {
+            ui.selectable_value(&mut lhm.scheme, "http".to_string(), "http");
+            ui.selectable_value(&mut lhm.scheme, "https".to_string(), "https");
+        });
+    ui.add(TextEdit::singleline(&mut lhm.host).hint_text("host"));
+    ui.horizontal(|ui| {
+        ui.label("Port:");
+        ui.add(DragValue::new(&mut lhm.port));
+    });
+    ui.add(TextEdit::singleline(&mut lhm.username).hint_text("user"));
+    ui.add(
+        TextEdit::singleline(&mut lhm.password)
+            .password(true)
+            .hint_text("password"),
+    );
+    ui.horizontal(|ui| {
+        ui.label("Timeout:");
+        ui.add(
+            DragValue::new(&mut lhm.timeout_ms)
+                .clamp_range(100..=10000)
+                .suffix("ms"),
+        );
+    });
+}
+
+fn show_prometheus_settings(prometheus: &mut PrometheusSettings, ui: &mut Ui) {
+    ui.checkbox(&mut prometheus.enabled, "read from node_exporter");
+    ui.add_enabled(
+        prometheus.enabled,
+        TextEdit::singleline(&mut prometheus.url).hint_text("metrics url"),
+    );
+    ui.add_enabled_ui(prometheus.enabled, |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Timeout:");
+            ui.add(
+                DragValue::new(&mut prometheus.timeout_ms)
+                    .clamp_range(100..=10000)
+                    .suffix("ms"),
+            );
+        });
+    });
+}
+
+fn show_ping_settings(targets: &mut Vec<PingTarget>, ui: &mut Ui) {
+    ui.label("Ping:");
+    let mut remove = None;
+    for (i, target) in targets.iter_mut().enumerate() {
+        ui.push_id(i, |ui| {
+            ui.horizontal(|ui| {
+                ui.add(
+                    TextEdit::singleline(&mut target.label)
+                        .hint_text("label")
+                        .desired_width(60.0),
+                );
+                ui.add(
+                    TextEdit::singleline(&mut target.host)
+                        .hint_text(match target.probe {
+                            Probe::Http => "url",
+                            Probe::Dns => "resolver",
+                            Probe::Icmp | Probe::Tcp => "host",
+                        })
+                        .desired_width(80.0),
+                );
+                if ui.small_button("x").clicked() {
+                    remove = Some(i);
+                }
+            });
+            ui.horizontal(|ui| {
+                ComboBox::from_id_source("probe")
+                    .selected_text(target.probe.to_string())
+                    .width(60.0)
+                    .show_ui(ui, |ui| {
+                        for probe in [Probe::Icmp, Probe::Tcp, Probe::Http, Probe::Dns] {
+                            ui.selectable_value(&mut target.probe, probe, probe.to_string());
+                        }
+                    });
+                match target.probe {
+                    Probe::Icmp => {
+                        // TTL is a single byte
+                        ui.add(
+                            DragValue::new(&mut target.hops)
+                                .clamp_range(1..=255)
+                                .prefix("hops "),
+                        );
+                    }
+                    Probe::Tcp => {
+                        ui.add(DragValue::new(&mut target.port).prefix("port "));
+                    }
+                    Probe::Http => {
+                        ui.add(
+                            DragValue::new(&mut target.expected_status)
+                                .clamp_range(100..=599)
+                                .prefix("expect "),
+                        );
+                    }
+                    Probe::Dns => {
+                        ui.add(
+                            TextEdit::singleline(&mut target.dns_name)
+                                .hint_text("name")
+                                .desired_width(60.0),
+                        );
+                        ui.checkbox(&mut target.dns_tcp, "TCP");
+                    }
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.add(
+                    DragValue::new(&mut target.interval_ms)
+                        .clamp_range(PING_INTERVAL_MS)
+                        .prefix("every ")
+                        .suffix("ms"),
+                );
+                ui.add(
+                    DragValue::new(&mut target.timeout_ms)
+                        .clamp_range(PING_TIMEOUT_MS)
+                        .prefix("timeout ")
+                        .suffix("ms"),
+                );
+            });
+        });
+    }
+    if let Some(i) = remove {
+        targets.remove(i);
+    }
+    if ui.button("add target").clicked() {
+        targets.push(PingTarget {
+            label: String::new(),
+            host: String::new(),
+            ..Default::default()
+        });
+    }
+}
+
 pub fn get_screen_size(appdata: &MyApp, scale_override: Option<f32>) {
     let mut settings = appdata.settings.lock();
     // let workarea_height = dbg!(unsafe { GetSystemMetrics(SM_CYFULLSCREEN) });
@@ -157,3 +521,21 @@ pub fn get_screen_size(appdata: &MyApp, scale_override: Option<f32>) {
         height,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ping_timing_is_clamped() {
+        let mut target: PingTarget =
+            serde_json::from_str(r#"{"host": "1.1.1.1", "interval_ms": 0, "timeout_ms": 60000}"#)
+                .unwrap();
+        target.validate();
+        assert_eq!((target.interval_ms, target.timeout_ms), (200, 10000));
+
+        let mut target = PingTarget::default();
+        target.validate();
+        assert_eq!(target, PingTarget::default());
+    }
+}
@@ -0,0 +1,56 @@
+use sysinfo::System;
+
+use super::{merge_gpus, GpuData, MetricSnapshot, MetricSource};
+
+use self::{nvidia_smi::NvidiaSmiProvider, nvml::NvmlProvider};
+
+pub mod nvidia_smi;
+pub mod nvml;
+
+/// A vendor specific way to read GPU stats.
+pub trait GpuProvider {
+    /// One entry per device the provider knows about. Values it can't read stay zero.
+    fn read(&mut self) -> Vec<GpuData>;
+}
+
+/// NVML, or nvidia-smi where NVML can't be loaded or finds no GPU. Querying both would start
+/// nvidia-smi every two seconds for nothing. `live` has NVML read the values LHM would provide.
+pub fn nvidia_provider(live: bool) -> Box<dyn GpuProvider> {
+    let nvml = NvmlProvider::load();
+    if nvml.device_count() == 0 {
+        Box::new(NvidiaSmiProvider::start())
+    } else if live {
+        Box::new(nvml.with_live_values())
+    } else {
+        Box::new(nvml)
+    }
+}
+
+/// Collects the GPUs of every provider. Providers that know the same GPU are merged like
+/// sources, the first one wins.
+pub struct GpuSource {
+    providers: Vec<Box<dyn GpuProvider>>,
+}
+
+impl GpuSource {
+    pub fn new(providers: Vec<Box<dyn GpuProvider>>) -> Self {
+        Self { providers }
+    }
+}
+
+impl MetricSource for GpuSource {
+    fn name(&self) -> &'static str {
+        "gpu"
+    }
+
+    fn refresh(&mut self, _system: &mut System) -> MetricSnapshot {
+        MetricSnapshot {
+            gpus: Some(
+                self.providers
+                    .iter_mut()
+                    .fold(vec![], |gpus, p| merge_gpus(gpus, p.read())),
+            ),
+            ..Default::default()
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use std::{
+    process::{Command, Output},
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+
+use itertools::Itertools;
+use parking_lot::Mutex;
+
+use crate::sources::{pci_bus_id, GpuData};
+
+use super::GpuProvider;
+
+/// The columns of [`parse_nvidia_smi`], in order.
+const QUERY: &str = "name,utilization.gpu,temperature.gpu,memory.used,memory.total,power.draw,\
+                     power.limit,fan.speed,clocks.gr,clocks.max.gr,pci.bus_id";
+const INTERVAL: Duration = Duration::from_secs(2);
+
+/// NVIDIA GPUs from `nvidia-smi`, for machines where NVML can't be loaded or finds no GPU. The
+/// tool takes too long to run on every refresh, a background thread queries it every two seconds.
+pub struct NvidiaSmiProvider {
+    latest: Arc<Mutex<Vec<GpuData>>>,
+}
+
+impl NvidiaSmiProvider {
+    pub fn start() -> Self {
+        let latest: Arc<Mutex<Vec<GpuData>>> = Default::default();
+        let thread_latest = latest.clone();
+        thread::spawn(move || query_thread(thread_latest));
+        Self { latest }
+    }
+}
+
+impl GpuProvider for NvidiaSmiProvider {
+    fn read(&mut self) -> Vec<GpuData> {
+        self.latest.lock().clone()
+    }
+}
+
+fn query_thread(latest: Arc<Mutex<Vec<GpuData>>>) {
+    let mut last_error = None;
+    loop {
+        let result = match query() {
+            Ok(output) => Ok(output),
+            // no NVIDIA driver, nothing to do
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return,
+            Err(e) => Err(e.to_string()),
+        }
+        .and_then(|output| {
+            if output.status.success() {
+                Ok(parse_nvidia_smi(&String::from_utf8_lossy(&output.stdout)))
+            } else {
+                // nvidia-smi explains what went wrong on stdout
+                Err(String::from_utf8_lossy(&output.stdout).trim().to_string())
+            }
+        });
+
+        match result {
+            Ok(gpus) => {
+                *latest.lock() = gpus;
+                last_error = None;
+            }
+            Err(e) => {
+                if last_error.as_ref() != Some(&e) {
+                    println!("nvidia-smi: {e}");
+                }
+                last_error = Some(e);
+                latest.lock().clear();
+            }
+        }
+        thread::sleep(INTERVAL);
+    }
+}
+
+fn query() -> std::io::Result<Output> {
+    let mut command = Command::new("nvidia-smi");
+    command
+        .arg(format!("--query-gpu={QUERY}"))
+        .arg("--format=csv,noheader,nounits");
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        // CREATE_NO_WINDOW, don't flash a console every two seconds
+        command.creation_flags(0x0800_0000);
+    }
+    command.output()
+}
+
+/// One GPU per line of `nvidia-smi --query-gpu=<QUERY> --format=csv,noheader,nounits`. Values
+/// the card doesn't support are `[N/A]` or `[Not Supported]` and stay zero:
+///
+/// ```text
+/// NVIDIA GeForce RTX 3080, 7, 45, 1234, 10240, 35.12, 320.00, 30, 210, 2100, 00000000:01:00.0
+/// NVIDIA GeForce GTX 1650, 0, 38, 5, 4096, [N/A], [N/A], [N/A], 300, 1950, 00000000:02:00.0
+/// ```
+pub fn parse_nvidia_smi(output: &str) -> Vec<GpuData> {
+    output
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .filter_map(|l| {
+            let columns = l.split(',').map(str::trim).collect_vec();
+            if columns.len() != QUERY.split(',').count() {
+                return None;
+            }
+            let num = |i: usize| columns[i].parse::<f32>().unwrap_or_default();
+            // MiB
+            let memory_used = num(3) * 1024.0 * 1024.0;
+            let memory_total = num(4) * 1024.0 * 1024.0;
+            Some(GpuData {
+                name: columns[0].to_string(),
+                bus_id: pci_bus_id(columns[10]),
+                utilization: num(1) as f64,
+                temperature: num(2),
+                memory_free: (memory_total - memory_used).max(0.0),
+                memory_used,
+                memory_total,
+                power_usage: num(5),
+                power_limit: num(6),
+                fan_percentage: num(7),
+                clock_mhz: num(8),
+                max_clock: num(9),
+            })
+        })
+        .collect_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Captured from a machine with two cards, the second one doesn't report power or fan.
+    const OUTPUT: &str = "\
+NVIDIA GeForce RTX 3080, 7, 45, 1234, 10240, 35.12, 320.00, 30, 210, 2100, 00000000:01:00.0
+NVIDIA GeForce GTX 1650, 0, 38, 5, 4096, [N/A], [N/A], [N/A], 300, 1950, 00000000:02:00.0
+";
+
+    #[test]
+    fn parses_every_gpu() {
+        let gpus = parse_nvidia_smi(OUTPUT);
+        assert_eq!(gpus.len(), 2);
+
+        let rtx = &gpus[0];
+        assert_eq!(rtx.name, "NVIDIA GeForce RTX 3080");
+        assert_eq!(rtx.bus_id, "0000:01:00.0");
+        assert_eq!(rtx.utilization, 7.0);
+        assert_eq!(rtx.temperature, 45.0);
+        assert_eq!(rtx.memory_used, 1234.0 * 1024.0 * 1024.0);
+        assert_eq!(rtx.memory_total, 10240.0 * 1024.0 * 1024.0);
+        assert_eq!(rtx.memory_free, (10240.0 - 1234.0) * 1024.0 * 1024.0);
+        assert_eq!(rtx.power_usage, 35.12);
+        assert_eq!(rtx.power_limit, 320.0);
+        assert_eq!(rtx.fan_percentage, 30.0);
+        assert_eq!((rtx.clock_mhz, rtx.max_clock), (210.0, 2100.0));
+
+        let gtx = &gpus[1];
+        assert_eq!(gtx.name, "NVIDIA GeForce GTX 1650");
+        assert_eq!(gtx.bus_id, "0000:02:00.0");
+        assert_eq!(
+            (gtx.power_usage, gtx.power_limit, gtx.fan_percentage),
+            (0.0, 0.0, 0.0)
+        );
+        assert_eq!((gtx.clock_mhz, gtx.max_clock), (300.0, 1950.0));
+    }
+
+    #[test]
+    fn skips_lines_that_dont_fit_the_query() {
+        let output =
+            "NVIDIA-SMI has failed because it couldn't communicate with the NVIDIA driver.\n\n\
+                      NVIDIA GeForce GTX 1650, [Not Supported], 38, 5, 4096, [N/A], [N/A], [N/A], \
+                      300, 1950, 00000000:02:00.0";
+        let gpus = parse_nvidia_smi(output);
+        assert_eq!(gpus.len(), 1);
+        assert_eq!(gpus[0].utilization, 0.0);
+        assert!(parse_nvidia_smi("").is_empty());
+    }
+}
@@ -0,0 +1,105 @@
+use nvml_wrapper::{
+    enum_wrappers::device::{Clock, TemperatureSensor},
+    Device, Nvml,
+};
+
+use crate::sources::{pci_bus_id, GpuData};
+
+use super::GpuProvider;
+
+/// Static NVIDIA limits. They are read once, querying NVML every second is too expensive. The
+/// live values come from LHM, unless the provider is told to read them too.
+pub struct NvmlProvider {
+    nvml: Option<Nvml>,
+    /// With the NVML index of each device, devices that fail to open are left out.
+    limits: Option<Vec<(u32, GpuData)>>,
+    live: bool,
+}
+
+impl NvmlProvider {
+    pub fn load() -> Self {
+        Self {
+            nvml: Nvml::init().ok(),
+            limits: None,
+            live: false,
+        }
+    }
+
+    /// Also read load, temperature, memory, power, fan and clock on every refresh, for systems
+    /// without LHM.
+    pub fn with_live_values(mut self) -> Self {
+        self.live = true;
+        self
+    }
+
+    /// 0 if NVML couldn't be loaded.
+    pub fn device_count(&self) -> u32 {
+        self.nvml
+            .as_ref()
+            .and_then(|nvml| nvml.device_count().ok())
+            .unwrap_or_default()
+    }
+}
+
+impl GpuProvider for NvmlProvider {
+    fn read(&mut self) -> Vec<GpuData> {
+        let Some(nvml) = self.nvml.as_ref() else {
+            return vec![];
+        };
+
+        let limits = self
+            .limits
+            .get_or_insert_with(|| {
+                (0..nvml.device_count().unwrap_or_default())
+                    .filter_map(|i| Some((i, nvml.device_by_index(i).ok()?)))
+                    .map(|(i, gpu)| {
+                        let limits = GpuData {
+                            name: gpu.name().unwrap_or_default(),
+                            bus_id: gpu
+                                .pci_info()
+                                .map(|p| pci_bus_id(&p.bus_id))
+                                .unwrap_or_default(),
+                            power_limit: gpu.enforced_power_limit().unwrap_or_default() as f32
+                                / 1000.0,
+                            max_clock: gpu.max_clock_info(Clock::Graphics).unwrap_or_default()
+                                as f32,
+                            ..Default::default()
+                        };
+                        (i, limits)
+                    })
+                    .collect()
+            })
+            .clone();
+        if !self.live {
+            return limits.into_iter().map(|(_, gpu)| gpu).collect();
+        }
+        limits
+            .into_iter()
+            .map(|(i, gpu)| match nvml.device_by_index(i) {
+                Ok(device) => read_live(&device, gpu),
+                Err(_) => gpu,
+            })
+            .collect()
+    }
+}
+
+fn read_live(device: &Device, gpu: GpuData) -> GpuData {
+    let memory = device.memory_info().ok();
+    GpuData {
+        utilization: device
+            .utilization_rates()
+            .map(|u| u.gpu as f64)
+            .unwrap_or_default(),
+        temperature: device
+            .temperature(TemperatureSensor::Gpu)
+            .unwrap_or_default() as f32,
+        memory_free: memory.as_ref().map(|m| m.free as f32).unwrap_or_default(),
+        memory_used: memory.as_ref().map(|m| m.used as f32).unwrap_or_default(),
+        memory_total: memory.as_ref().map(|m| m.total as f32).unwrap_or_default(),
+        // mW
+        power_usage: device.power_usage().unwrap_or_default() as f32 / 1000.0,
+        fan_percentage: device.fan_speed(0).unwrap_or_default() as f32,
+        clock_mhz: device.clock_info(Clock::Graphics).unwrap_or_default() as f32,
+        ..gpu
+    }
+}
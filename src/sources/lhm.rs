@@ -0,0 +1,231 @@
+use std::{collections::HashSet, sync::Arc};
+
+use itertools::Itertools;
+use parking_lot::Mutex;
+use sysinfo::System;
+
+use crate::{
+    lm_sensors::is_cpu_chip,
+    ohw::{MyNode, OHWNode, SensorError},
+    quantity::Unit,
+};
+
+use super::{BatteryData, GpuData, MetricSnapshot, MetricSource};
+
+/// Reads the LibreHardwareMonitor tree last fetched by the `ohw_thread`.
+pub struct LhmSource {
+    ohw_info: Arc<Mutex<Option<OHWNode>>>,
+    /// Errors already printed, so a broken sensor doesn't spam the log every second.
+    reported: HashSet<String>,
+}
+
+impl LhmSource {
+    pub fn new(ohw_info: Arc<Mutex<Option<OHWNode>>>) -> Self {
+        Self {
+            ohw_info,
+            reported: HashSet::new(),
+        }
+    }
+}
+
+impl MetricSource for LhmSource {
+    fn name(&self) -> &'static str {
+        "lhm"
+    }
+
+    fn refresh(&mut self, _system: &mut System) -> MetricSnapshot {
+        let ohw = self.ohw_info.lock();
+        let Some(ohw) = ohw.as_ref() else {
+            return Default::default();
+        };
+        let mut r = Reader::default();
+
+        let cpu = hardware(ohw, Hardware::Cpu).next();
+        let (ram_used, ram_total) = read_memory(&mut r, ohw);
+
+        let snapshot = MetricSnapshot {
+            core_temps: cpu.and_then(|cpu| read_core_temps(&mut r, cpu)),
+            cpu_temp: cpu
+                .and_then(|cpu| {
+                    r.get(cpu, "Temperatures|Package", Unit::Celsius)
+                        .or_else(|| r.get(cpu, "Temperatures|Tctl", Unit::Celsius))
+                })
+                .map(|v| v as f32),
+            cpu_power: cpu.and_then(|cpu| r.get(cpu, "Power|Package", Unit::Watt)),
+            ram_used,
+            ram_total,
+            gpus: read_gpus(&mut r, ohw),
+            battery: read_battery(&mut r, ohw),
+            ..Default::default()
+        };
+
+        for e in r.errors {
+            if self.reported.insert(e.to_string()) {
+                println!("LHM: {e}");
+            }
+        }
+        snapshot
+    }
+}
+
+/// Collects everything that went wrong while reading one tree. Missing sensors are expected,
+/// not every machine has every sensor, so only values that exist but can't be used are errors.
+#[derive(Default)]
+struct Reader {
+    errors: Vec<SensorError>,
+}
+
+impl Reader {
+    fn get(&mut self, node: &OHWNode, path: &str, unit: Unit) -> Option<f64> {
+        match node.value(path, unit) {
+            Ok(v) => Some(v),
+            Err(SensorError::NotFound(_)) => None,
+            Err(e) => {
+                self.errors.push(e);
+                None
+            }
+        }
+    }
+
+    fn get_def(&mut self, node: &OHWNode, path: &str, unit: Unit) -> f32 {
+        self.get(node, path, unit).unwrap_or_default() as f32
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Hardware {
+    Cpu,
+    Memory,
+    NvidiaGpu,
+    OtherGpu,
+    Battery,
+}
+
+impl Hardware {
+    /// Newer LHM versions send a HardwareId like `/gpu-nvidia/0`, older ones only tell the kind
+    /// of hardware by its icon.
+    fn of(n: &OHWNode) -> Option<Hardware> {
+        if let Some(id) = &n.HardwareId {
+            let mut parts = id.trim_start_matches('/').split('/');
+            return match parts.next()? {
+                "amdcpu" | "intelcpu" => Some(Hardware::Cpu),
+                "lm-sensors" => parts
+                    .next()
+                    .filter(|chip| is_cpu_chip(chip))
+                    .map(|_| Hardware::Cpu),
+                "ram" => Some(Hardware::Memory),
+                "gpu-nvidia" => Some(Hardware::NvidiaGpu),
+                k if k.starts_with("gpu-") => Some(Hardware::OtherGpu),
+                "battery" => Some(Hardware::Battery),
+                _ => None,
+            };
+        }
+        match n.ImageURL.as_str() {
+            "images_icon/cpu.png" => Some(Hardware::Cpu),
+            "images_icon/nvidia.png" => Some(Hardware::NvidiaGpu),
+            "images_icon/ati.png" | "images_icon/intel.png" => Some(Hardware::OtherGpu),
+            "images_icon/battery.png" => Some(Hardware::Battery),
+            _ if n.Text.contains("Generic Memory") => Some(Hardware::Memory),
+            _ => None,
+        }
+    }
+}
+
+/// All hardware of one kind, in LHM's order.
+fn hardware(ohw: &OHWNode, kind: Hardware) -> impl Iterator<Item = &OHWNode> {
+    ohw.Children
+        .iter()
+        .take(1)
+        .flat_map(|computer| computer.Children.iter())
+        .filter(move |n| Hardware::of(n) == Some(kind))
+}
+
+fn read_core_temps(r: &mut Reader, cpu: &OHWNode) -> Option<Vec<(i32, f32)>> {
+    let temps = cpu.select("Temperatures")?;
+    Some(
+        temps
+            .select_all("CPU Core #*")
+            .into_iter()
+            // lm-sensors' coretemp labels
+            .chain(temps.select_all("Core *"))
+            .filter_map(|n| {
+                let core = n
+                    .Text
+                    .strip_prefix("CPU Core #")
+                    .or_else(|| n.Text.strip_prefix("Core "))?
+                    .parse::<i32>()
+                    .ok()?;
+                Some((core, r.get(n, "", Unit::Celsius)? as f32))
+            })
+            .collect_vec(),
+    )
+}
+
+fn read_memory(r: &mut Reader, ohw: &OHWNode) -> (Option<f32>, Option<f32>) {
+    let Some(nodes) = hardware(ohw, Hardware::Memory)
+        .next()
+        .and_then(|n| n.select("Data"))
+    else {
+        return (None, None);
+    };
+    let used = r.get(nodes, "Memory Used", Unit::Byte);
+    let available = r.get(nodes, "Memory Available", Unit::Byte);
+    let total = used.zip(available).map(|(u, a)| u + a);
+    (used.map(|v| v as f32), total.map(|v| v as f32))
+}
+
+/// Every GPU node LHM lists. For NVIDIA these are live values only, NVML knows the limits.
+fn read_gpus(r: &mut Reader, ohw: &OHWNode) -> Option<Vec<GpuData>> {
+    let mut gpus = hardware(ohw, Hardware::NvidiaGpu)
+        .map(|n| read_nvidia_gpu(r, n))
+        .collect_vec();
+    gpus.extend(hardware(ohw, Hardware::OtherGpu).map(|n| read_other_gpu(r, n)));
+    (!gpus.is_empty()).then_some(gpus)
+}
+
+fn read_nvidia_gpu(r: &mut Reader, n: &OHWNode) -> GpuData {
+    GpuData {
+        name: n.Text.clone(),
+        utilization: r.get(n, "Load|#0", Unit::Percent).unwrap_or_default(),
+        temperature: r.get_def(n, "Temperatures|#0", Unit::Celsius),
+        memory_free: r.get_def(n, "Data|#0", Unit::Byte),
+        memory_used: r.get_def(n, "Data|#1", Unit::Byte),
+        memory_total: r.get_def(n, "Data|#2", Unit::Byte),
+        power_usage: r.get_def(n, "Powers|#0", Unit::Watt),
+        fan_percentage: r.get_def(n, "Controls|#0", Unit::Percent),
+        clock_mhz: r.get_def(n, "Clocks|#0", Unit::Hertz) / 1e6,
+        ..Default::default()
+    }
+}
+
+/// AMD and Intel GPUs don't share NVIDIA's sensor order, look them up by name instead.
+fn read_other_gpu(r: &mut Reader, n: &OHWNode) -> GpuData {
+    GpuData {
+        name: n.Text.clone(),
+        utilization: r.get(n, "Load|GPU Core", Unit::Percent).unwrap_or_default(),
+        temperature: r.get_def(n, "Temperatures|GPU", Unit::Celsius),
+        memory_free: r.get_def(n, "Data|GPU Memory Free", Unit::Byte),
+        memory_used: r.get_def(n, "Data|GPU Memory Used", Unit::Byte),
+        memory_total: r.get_def(n, "Data|GPU Memory Total", Unit::Byte),
+        power_usage: r.get_def(n, "Powers|GPU", Unit::Watt),
+        fan_percentage: r.get_def(n, "Controls|GPU Fan", Unit::Percent),
+        clock_mhz: r.get_def(n, "Clocks|GPU Core", Unit::Hertz) / 1e6,
+        ..Default::default()
+    }
+}
+
+fn read_battery(r: &mut Reader, ohw: &OHWNode) -> Option<BatteryData> {
+    let battery = hardware(ohw, Hardware::Battery).next()?;
+    let level = r.get(battery, "levels|charge", Unit::Percent)?;
+    let charge_rate = r
+        .get(battery, "currents|discharge", Unit::Ampere)
+        .filter(|a| *a != 0.0)
+        .map(|a| -a)
+        .or_else(|| r.get(battery, "currents|charge", Unit::Ampere))
+        .unwrap_or_default();
+    Some(BatteryData {
+        level,
+        charge_rate,
+        ..Default::default()
+    })
+}
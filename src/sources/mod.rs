@@ -0,0 +1,335 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+use sysinfo::System;
+
+use crate::{
+    process::Process,
+    quantity::{Quantity, Unit},
+};
+
+pub mod gpu;
+pub mod lhm;
+pub mod prometheus;
+pub mod system;
+
+/// A reader for one group of system metrics.
+///
+/// Sources are asked for a fresh snapshot once per refresh. The snapshots of all sources are
+/// merged in order, so a source earlier in the list wins for any value both of them provide.
+pub trait MetricSource {
+    /// Short name used for perf tracing.
+    fn name(&self) -> &'static str;
+
+    /// Called once before the first refresh, after the sysinfo disk and cpu lists are loaded.
+    fn init(&mut self, _system: &mut System) {}
+
+    /// `system` is the sysinfo instance shared with the rendering code.
+    fn refresh(&mut self, system: &mut System) -> MetricSnapshot;
+}
+
+/// Everything a refresh can produce. `None` means "not provided by this source".
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct MetricSnapshot {
+    pub cpu_usage: Option<f32>,
+    pub core_usage: Option<Vec<f32>>,
+    /// (core index, °C)
+    pub core_temps: Option<Vec<(i32, f32)>>,
+    /// °C, package sensor. Only used when there are no per core temperatures.
+    pub cpu_temp: Option<f32>,
+    /// W
+    pub cpu_power: Option<f64>,
+    /// W, configured package limit
+    pub cpu_power_limit: Option<f64>,
+    /// bytes
+    pub ram_used: Option<f32>,
+    /// bytes
+    pub ram_total: Option<f32>,
+    pub gpus: Option<Vec<GpuData>>,
+    pub drives: Option<Vec<DriveData>>,
+    pub networks: Option<Vec<(String, NetworkData)>>,
+    pub processes: Option<Vec<Process>>,
+    pub battery: Option<BatteryData>,
+}
+
+impl MetricSnapshot {
+    /// Fill every value missing in `self` from `other`.
+    pub fn merge(&mut self, other: MetricSnapshot) {
+        self.cpu_usage = self.cpu_usage.or(other.cpu_usage);
+        self.core_usage = self.core_usage.take().or(other.core_usage);
+        self.core_temps = self.core_temps.take().or(other.core_temps);
+        self.cpu_temp = self.cpu_temp.or(other.cpu_temp);
+        self.cpu_power = self.cpu_power.or(other.cpu_power);
+        self.cpu_power_limit = self.cpu_power_limit.or(other.cpu_power_limit);
+        self.ram_used = self.ram_used.or(other.ram_used);
+        self.ram_total = self.ram_total.or(other.ram_total);
+        self.gpus = match (self.gpus.take(), other.gpus) {
+            (Some(a), Some(b)) => Some(merge_gpus(a, b)),
+            (a, b) => a.or(b),
+        };
+        self.drives = match (self.drives.take(), other.drives) {
+            (Some(a), Some(b)) => Some(merge_drives(a, b)),
+            (a, b) => a.or(b),
+        };
+        self.networks = self.networks.take().or(other.networks);
+        self.processes = self.processes.take().or(other.processes);
+        self.battery = self.battery.take().or(other.battery);
+    }
+
+    /// A single value by name, for custom tiles.
+    pub fn metric(&self, name: &str) -> Option<Quantity> {
+        let (value, unit) = match name {
+            "cpu_usage" => (self.cpu_usage? as f64, Unit::Percent),
+            "cpu_temp" => (self.cpu_temp? as f64, Unit::Celsius),
+            "cpu_power" => (self.cpu_power?, Unit::Watt),
+            "cpu_power_limit" => (self.cpu_power_limit?, Unit::Watt),
+            "ram_used" => (self.ram_used? as f64, Unit::Byte),
+            "ram_total" => (self.ram_total? as f64, Unit::Byte),
+            "battery_level" => (self.battery.as_ref()?.level, Unit::Percent),
+            _ => return None,
+        };
+        Some(Quantity { value, unit })
+    }
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[allow(dead_code)]
+pub struct GpuData {
+    pub name: String,
+    /// PCI address like `0000:01:00.0`, empty if the source can't tell. See [`pci_bus_id`].
+    /// Recordings from before it existed don't have it.
+    #[serde(default)]
+    pub bus_id: String,
+    pub utilization: f64,
+    pub temperature: f32,
+    pub memory_free: f32,
+    pub memory_used: f32,
+    pub memory_total: f32,
+    pub power_usage: f32,
+    pub power_limit: f32,
+    pub fan_percentage: f32,
+    pub clock_mhz: f32,
+    pub max_clock: f32,
+}
+
+impl GpuData {
+    /// Take every field that is still zero from `other`.
+    fn merge(self, other: GpuData) -> GpuData {
+        GpuData {
+            name: or_nonzero(self.name, other.name),
+            bus_id: or_nonzero(self.bus_id, other.bus_id),
+            utilization: or_nonzero(self.utilization, other.utilization),
+            temperature: or_nonzero(self.temperature, other.temperature),
+            memory_free: or_nonzero(self.memory_free, other.memory_free),
+            memory_used: or_nonzero(self.memory_used, other.memory_used),
+            memory_total: or_nonzero(self.memory_total, other.memory_total),
+            power_usage: or_nonzero(self.power_usage, other.power_usage),
+            power_limit: or_nonzero(self.power_limit, other.power_limit),
+            fan_percentage: or_nonzero(self.fan_percentage, other.fan_percentage),
+            clock_mhz: or_nonzero(self.clock_mhz, other.clock_mhz),
+            max_clock: or_nonzero(self.max_clock, other.max_clock),
+        }
+    }
+}
+
+/// NVML and nvidia-smi print the domain with 8 digits (`00000000:01:00.0`), sysfs with 4
+/// (`0000:01:00.0`). Both become the sysfs form, a domain never needs more than 4.
+pub fn pci_bus_id(raw: &str) -> String {
+    let raw = raw.trim().to_lowercase();
+    match raw.split_once(':') {
+        Some((domain, rest)) if domain.len() > 4 => {
+            format!("{}:{rest}", &domain[domain.len() - 4..])
+        }
+        _ => raw,
+    }
+}
+
+/// GPUs are matched by PCI bus id. When one side doesn't know the bus id, e.g. LHM, the n-th GPU
+/// of a name matches the n-th one of the same name, so identical cards stay apart. An unnamed
+/// GPU is matched by position, since that is all a source without names can know.
+fn merge_gpus(mut a: Vec<GpuData>, b: Vec<GpuData>) -> Vec<GpuData> {
+    // every GPU of `a` takes at most one of `b`
+    let mut matched = vec![false; a.len()];
+    for (i, gpu) in b.into_iter().enumerate() {
+        let by_bus_id = (!gpu.bus_id.is_empty())
+            .then(|| a.iter().position(|g| g.bus_id == gpu.bus_id))
+            .flatten();
+        let existing = by_bus_id.or_else(|| {
+            let free = |j: usize| !matched[j] && (a[j].bus_id.is_empty() || gpu.bus_id.is_empty());
+            if gpu.name.is_empty() {
+                Some(i).filter(|i| *i < a.len() && free(*i))
+            } else {
+                (0..a.len()).find(|j| free(*j) && a[*j].name.eq_ignore_ascii_case(&gpu.name))
+            }
+        });
+        match existing {
+            Some(idx) => {
+                a[idx] = std::mem::take(&mut a[idx]).merge(gpu);
+                matched[idx] = true;
+            }
+            None => {
+                a.push(gpu);
+                matched.push(true);
+            }
+        }
+    }
+    a
+}
+
+fn or_nonzero<T: PartialEq + Default>(a: T, b: T) -> T {
+    if a != T::default() {
+        a
+    } else {
+        b
+    }
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct DriveData {
+    /// Mount point as displayed, e.g. `C:`
+    pub mount: String,
+    pub total_space: u64,
+    pub available_space: u64,
+    /// % disk time
+    pub busy: Option<f64>,
+    pub read_bytes_per_sec: Option<f64>,
+    pub write_bytes_per_sec: Option<f64>,
+    pub iops: Option<f64>,
+}
+
+/// Drives are matched by mount point, drives only known to `b` are appended.
+fn merge_drives(mut a: Vec<DriveData>, b: Vec<DriveData>) -> Vec<DriveData> {
+    for drive in b {
+        if let Some(existing) = a.iter_mut().find(|d| d.mount == drive.mount) {
+            existing.total_space = or_nonzero(existing.total_space, drive.total_space);
+            existing.available_space = or_nonzero(existing.available_space, drive.available_space);
+            existing.busy = existing.busy.or(drive.busy);
+            existing.read_bytes_per_sec = existing.read_bytes_per_sec.or(drive.read_bytes_per_sec);
+            existing.write_bytes_per_sec =
+                existing.write_bytes_per_sec.or(drive.write_bytes_per_sec);
+            existing.iops = existing.iops.or(drive.iops);
+        } else {
+            a.push(drive);
+        }
+    }
+    a
+}
+
+/// Bytes since the last refresh.
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct NetworkData {
+    pub tx: f64,
+    pub rx: f64,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct BatteryData {
+    /// %
+    pub level: f64,
+    /// A, negative while discharging
+    pub charge_rate: f64,
+    /// W, negative while discharging
+    pub power: Option<f64>,
+    pub status: BatteryStatus,
+    pub capacity: Option<BatteryCapacity>,
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BatteryStatus {
+    #[default]
+    Unknown,
+    Charging,
+    Discharging,
+    NotCharging,
+    Full,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatteryCapacity {
+    pub full: f64,
+    pub design: f64,
+    pub unit: CapacityUnit,
+}
+
+/// Batteries report their capacity either as energy or as charge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CapacityUnit {
+    Wh,
+    Ah,
+}
+
+impl Display for CapacityUnit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CapacityUnit::Wh => write!(f, "Wh"),
+            CapacityUnit::Ah => write!(f, "Ah"),
+        }
+    }
+}
+
+impl BatteryCapacity {
+    /// Full charge capacity as % of the design capacity.
+    pub fn health(&self) -> f64 {
+        self.full / self.design.max(f64::EPSILON) * 100.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gpu(name: &str, bus_id: &str, power_limit: f32) -> GpuData {
+        GpuData {
+            name: name.to_string(),
+            bus_id: bus_id.to_string(),
+            power_limit,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn normalizes_bus_ids() {
+        assert_eq!(pci_bus_id("00000000:0A:00.0"), "0000:0a:00.0");
+        assert_eq!(pci_bus_id("0000:03:00.0\n"), "0000:03:00.0");
+        assert_eq!(pci_bus_id("00000001:01:00.0"), "0001:01:00.0");
+    }
+
+    #[test]
+    fn identical_cards_are_kept_apart_by_bus_id() {
+        let a = vec![
+            gpu("RTX 3080", "0000:01:00.0", 0.0),
+            gpu("RTX 3080", "0000:02:00.0", 0.0),
+        ];
+        let b = vec![
+            gpu("RTX 3080", "0000:02:00.0", 320.0),
+            gpu("RTX 3080", "0000:01:00.0", 340.0),
+        ];
+        let merged = merge_gpus(a, b);
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].power_limit, 340.0);
+        assert_eq!(merged[1].power_limit, 320.0);
+    }
+
+    #[test]
+    fn identical_cards_without_bus_id_match_in_order() {
+        // LHM knows no bus ids
+        let a = vec![gpu("RTX 3080", "", 0.0), gpu("RTX 3080", "", 0.0)];
+        let b = vec![
+            gpu("RTX 3080", "0000:01:00.0", 340.0),
+            gpu("RTX 3080", "0000:02:00.0", 320.0),
+        ];
+        let merged = merge_gpus(a, b);
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].bus_id, "0000:01:00.0");
+        assert_eq!(merged[0].power_limit, 340.0);
+        assert_eq!(merged[1].bus_id, "0000:02:00.0");
+        assert_eq!(merged[1].power_limit, 320.0);
+    }
+
+    #[test]
+    fn different_bus_ids_are_different_cards() {
+        let a = vec![gpu("AMD GPU (card0)", "0000:03:00.0", 0.0)];
+        let b = vec![gpu("", "0000:01:00.0", 250.0)];
+        assert_eq!(merge_gpus(a, b).len(), 2);
+    }
+}
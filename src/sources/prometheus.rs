@@ -0,0 +1,590 @@
+use std::{collections::BTreeMap, sync::Arc, time::Instant};
+
+use chrono::{Duration, Local};
+use itertools::Itertools;
+use parking_lot::Mutex;
+use sysinfo::System;
+use tokio::time::sleep;
+
+use crate::settings::MySettings;
+
+use super::{DriveData, MetricSnapshot, MetricSource, NetworkData};
+
+/// Filesystems that aren't drives.
+const IGNORED_FSTYPES: [&str; 9] = [
+    "tmpfs", "devtmpfs", "overlay", "squashfs", "proc", "sysfs", "ramfs", "autofs", "nsfs",
+];
+
+/// Chips `node_hwmon_chip_names` reports for CPU sensors.
+const CPU_CHIPS: [&str; 3] = ["coretemp", "k10temp", "zenpower"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub name: String,
+    pub labels: Vec<(String, String)>,
+    pub value: f64,
+}
+
+impl Sample {
+    fn label(&self, key: &str) -> Option<&str> {
+        self.labels
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Scrape {
+    at: Instant,
+    samples: Vec<Sample>,
+}
+
+impl Scrape {
+    fn series<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Sample> {
+        self.samples.iter().filter(move |s| s.name == name)
+    }
+
+    fn value(&self, name: &str) -> Option<f64> {
+        self.series(name).next().map(|s| s.value)
+    }
+}
+
+/// Parses the Prometheus text exposition format. Comments, lines that don't parse and values that
+/// aren't finite are skipped.
+pub fn parse_exposition(text: &str) -> Vec<Sample> {
+    text.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .filter_map(parse_line)
+        .collect_vec()
+}
+
+fn parse_line(line: &str) -> Option<Sample> {
+    let name_end = line.find(|c: char| c == '{' || c.is_whitespace())?;
+    let name = line[..name_end].to_string();
+    let mut rest = &line[name_end..];
+
+    let mut labels = vec![];
+    if let Some(r) = rest.strip_prefix('{') {
+        rest = r;
+        loop {
+            rest = rest.trim_start_matches([',', ' ']);
+            if let Some(r) = rest.strip_prefix('}') {
+                rest = r;
+                break;
+            }
+            let (key, r) = rest.split_once('=')?;
+            let (value, r) = parse_quoted(r.trim_start())?;
+            labels.push((key.trim().to_string(), value));
+            rest = r;
+        }
+    }
+
+    // an optional timestamp may follow the value
+    let value = rest.split_whitespace().next()?;
+    // NaN and ±Inf can't be shown and would break comparisons later on
+    let value = value.parse::<f64>().ok().filter(|v| v.is_finite())?;
+    Some(Sample {
+        name,
+        labels,
+        value,
+    })
+}
+
+/// `"a\"b" rest` -> (`a"b`, ` rest`)
+fn parse_quoted(s: &str) -> Option<(String, &str)> {
+    let s = s.strip_prefix('"')?;
+    let mut value = String::new();
+    let mut chars = s.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((value, &s[i + 1..])),
+            '\\' => match chars.next()?.1 {
+                'n' => value.push('\n'),
+                c => value.push(c),
+            },
+            c => value.push(c),
+        }
+    }
+    None
+}
+
+/// Scrapes the configured endpoint once a second while the source is enabled.
+pub async fn prometheus_thread(
+    latest: Arc<Mutex<Option<Scrape>>>,
+    settings: Arc<Mutex<MySettings>>,
+) -> ! {
+    let mut client: Option<(u64, reqwest::Client)> = None;
+    let mut last_error = None;
+    loop {
+        let prometheus = settings.lock().current_settings.prometheus.clone();
+        // a changed timeout applies without a restart
+        if client.as_ref().map(|(t, _)| *t) != Some(prometheus.timeout_ms) {
+            let c = reqwest::Client::builder()
+                .timeout(std::time::Duration::from_millis(prometheus.timeout_ms))
+                .build()
+                .unwrap_or_default();
+            client = Some((prometheus.timeout_ms, c));
+        }
+        let (_, client) = client.as_ref().unwrap();
+        if prometheus.enabled {
+            let result = match client.get(&prometheus.url).send().await {
+                Ok(response) => response.error_for_status().map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            let result = match result {
+                Ok(response) => response.text().await.map_err(|e| e.to_string()),
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(text) => {
+                    *latest.lock() = Some(Scrape {
+                        at: Instant::now(),
+                        samples: parse_exposition(&text),
+                    });
+                    last_error = None;
+                }
+                Err(e) => {
+                    if last_error.as_ref() != Some(&e) {
+                        println!("Prometheus {}: {e}", prometheus.url);
+                    }
+                    last_error = Some(e);
+                    *latest.lock() = None;
+                }
+            }
+        } else {
+            *latest.lock() = None;
+        }
+
+        sleep(
+            Duration::milliseconds(
+                (1000
+                    - Local::now()
+                        .naive_local()
+                        .and_utc()
+                        .timestamp_subsec_millis() as i64)
+                    .clamp(520, 999),
+            )
+            .to_std()
+            .unwrap(),
+        )
+        .await;
+    }
+}
+
+/// CPU, memory, temperatures, network and drive space from a node_exporter. Counters are read as
+/// differences between two scrapes, so the first scrape only provides the gauges.
+pub struct PrometheusSource {
+    latest: Arc<Mutex<Option<Scrape>>>,
+    previous: Option<Scrape>,
+    last_snapshot: MetricSnapshot,
+}
+
+impl PrometheusSource {
+    pub fn new(latest: Arc<Mutex<Option<Scrape>>>) -> Self {
+        Self {
+            latest,
+            previous: None,
+            last_snapshot: Default::default(),
+        }
+    }
+}
+
+impl PrometheusSource {
+    /// The last snapshot again, without counting its traffic a second time. The devices stay,
+    /// with no traffic, so the local interfaces don't stand in for them until the next scrape.
+    fn repeated_snapshot(&self) -> MetricSnapshot {
+        let mut snapshot = self.last_snapshot.clone();
+        if let Some(networks) = &mut snapshot.networks {
+            for (_, data) in networks {
+                *data = NetworkData::default();
+            }
+        }
+        snapshot
+    }
+}
+
+impl MetricSource for PrometheusSource {
+    fn name(&self) -> &'static str {
+        "prometheus"
+    }
+
+    fn refresh(&mut self, _system: &mut System) -> MetricSnapshot {
+        let Some(scrape) = self.latest.lock().clone() else {
+            self.previous = None;
+            return Default::default();
+        };
+        // the scrape thread didn't finish a new one since the last refresh
+        if self.previous.as_ref().map(|p| p.at) == Some(scrape.at) {
+            return self.repeated_snapshot();
+        }
+
+        let (core_temps, cpu_temp) = read_temps(&scrape);
+        let mut snapshot = MetricSnapshot {
+            core_temps,
+            cpu_temp,
+            ram_total: scrape.value("node_memory_MemTotal_bytes").map(|v| v as f32),
+            ram_used: scrape
+                .value("node_memory_MemTotal_bytes")
+                .zip(scrape.value("node_memory_MemAvailable_bytes"))
+                .map(|(total, available)| (total - available) as f32),
+            drives: read_drives(&scrape),
+            ..Default::default()
+        };
+
+        if let Some(previous) = &self.previous {
+            let core_usage = read_core_usage(previous, &scrape);
+            if !core_usage.is_empty() {
+                snapshot.cpu_usage = Some(core_usage.iter().sum::<f32>() / core_usage.len() as f32);
+                snapshot.core_usage = Some(core_usage);
+            }
+            snapshot.networks = Some(read_networks(previous, &scrape));
+        }
+
+        self.previous = Some(scrape);
+        self.last_snapshot = snapshot.clone();
+        snapshot
+    }
+}
+
+/// Busy % per core from the time each core spent in every mode.
+fn read_core_usage(previous: &Scrape, current: &Scrape) -> Vec<f32> {
+    // core -> (total, idle) seconds
+    let times = |s: &Scrape| {
+        let mut times = BTreeMap::<u32, (f64, f64)>::new();
+        for sample in s.series("node_cpu_seconds_total") {
+            let Some(cpu) = sample.label("cpu").and_then(|c| c.parse().ok()) else {
+                continue;
+            };
+            let entry = times.entry(cpu).or_default();
+            entry.0 += sample.value;
+            if matches!(sample.label("mode"), Some("idle") | Some("iowait")) {
+                entry.1 += sample.value;
+            }
+        }
+        times
+    };
+    let before = times(previous);
+    times(current)
+        .into_iter()
+        .map(|(cpu, (total, idle))| {
+            let (last_total, last_idle) = before.get(&cpu).copied().unwrap_or_default();
+            let total = total - last_total;
+            let idle = idle - last_idle;
+            if total <= 0.0 {
+                0.0
+            } else {
+                ((1.0 - idle / total) * 100.0).clamp(0.0, 100.0) as f32
+            }
+        })
+        .collect_vec()
+}
+
+/// Core temperatures by their hwmon label (`Core 3`), the package temperature from `Package id
+/// 0`, `Tctl` or `Tdie`. Without labels the hottest sensor of a CPU chip stands in for the
+/// package.
+fn read_temps(scrape: &Scrape) -> (Option<Vec<(i32, f32)>>, Option<f32>) {
+    let label_of = |chip: &str, sensor: &str| {
+        scrape
+            .series("node_hwmon_sensor_label")
+            .find(|s| s.label("chip") == Some(chip) && s.label("sensor") == Some(sensor))
+            .and_then(|s| s.label("label"))
+    };
+    let cpu_chips = scrape
+        .series("node_hwmon_chip_names")
+        .filter(|s| CPU_CHIPS.contains(&s.label("chip_name").unwrap_or_default()))
+        .filter_map(|s| s.label("chip"))
+        .collect_vec();
+
+    let mut cores = vec![];
+    let mut package = None;
+    let mut hottest: Option<f32> = None;
+    for t in scrape.series("node_hwmon_temp_celsius") {
+        let (Some(chip), Some(sensor)) = (t.label("chip"), t.label("sensor")) else {
+            continue;
+        };
+        let value = t.value as f32;
+        match label_of(chip, sensor) {
+            Some(label) if label.starts_with("Core ") => {
+                if let Ok(core) = label["Core ".len()..].parse() {
+                    cores.push((core, value));
+                }
+            }
+            Some("Package id 0") | Some("Tctl") | Some("Tdie") => {
+                package = package.or(Some(value));
+            }
+            _ => {}
+        }
+        if cpu_chips.contains(&chip) {
+            hottest = Some(hottest.map_or(value, |h| h.max(value)));
+        }
+    }
+    cores.sort_by_key(|(core, _)| *core);
+    ((!cores.is_empty()).then_some(cores), package.or(hottest))
+}
+
+/// Bytes since the previous scrape, like every other source reports them per refresh.
+fn read_networks(previous: &Scrape, current: &Scrape) -> Vec<(String, NetworkData)> {
+    let delta = |name: &str, device: &str| {
+        let get = |s: &Scrape| {
+            s.series(name)
+                .find(|s| s.label("device") == Some(device))
+                .map(|s| s.value)
+        };
+        match (get(previous), get(current)) {
+            // counters reset when the exporter restarts
+            (Some(before), Some(now)) if now >= before => now - before,
+            _ => 0.0,
+        }
+    };
+    current
+        .series("node_network_receive_bytes_total")
+        .filter_map(|s| s.label("device"))
+        .filter(|d| *d != "lo")
+        .unique()
+        .map(|device| {
+            (
+                device.to_string(),
+                NetworkData {
+                    tx: delta("node_network_transmit_bytes_total", device),
+                    rx: delta("node_network_receive_bytes_total", device),
+                },
+            )
+        })
+        .collect_vec()
+}
+
+fn read_drives(scrape: &Scrape) -> Option<Vec<DriveData>> {
+    let drives = scrape
+        .series("node_filesystem_size_bytes")
+        .filter(|s| !IGNORED_FSTYPES.contains(&s.label("fstype").unwrap_or_default()))
+        .filter_map(|size| {
+            let mount = size.label("mountpoint")?;
+            let available = scrape
+                .series("node_filesystem_avail_bytes")
+                .find(|a| a.label("mountpoint") == Some(mount))?;
+            Some(DriveData {
+                mount: mount.to_string(),
+                total_space: size.value as u64,
+                available_space: available.value as u64,
+                ..Default::default()
+            })
+        })
+        .unique_by(|d| d.mount.clone())
+        .collect_vec();
+    (!drives.is_empty()).then_some(drives)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    use sysinfo::SystemExt;
+
+    fn scrape(text: &str) -> Scrape {
+        Scrape {
+            at: Instant::now(),
+            samples: parse_exposition(text),
+        }
+    }
+
+    #[test]
+    fn network_traffic_is_bytes_between_scrapes() {
+        let previous = scrape(
+            r#"
+node_network_receive_bytes_total{device="eth0"} 1000
+node_network_transmit_bytes_total{device="eth0"} 500
+node_network_receive_bytes_total{device="lo"} 10
+node_network_receive_bytes_total{device="wlan0"} 9000
+node_network_transmit_bytes_total{device="wlan0"} 9000
+"#,
+        );
+        let current = scrape(
+            r#"
+node_network_receive_bytes_total{device="eth0"} 4000
+node_network_transmit_bytes_total{device="eth0"} 700
+node_network_receive_bytes_total{device="lo"} 20
+node_network_receive_bytes_total{device="wlan0"} 100
+node_network_transmit_bytes_total{device="wlan0"} 100
+"#,
+        );
+        let networks = read_networks(&previous, &current);
+        assert_eq!(networks.len(), 2);
+        assert_eq!(networks[0].0, "eth0");
+        assert_eq!((networks[0].1.rx, networks[0].1.tx), (3000.0, 200.0));
+        // the exporter restarted
+        assert_eq!(networks[1].0, "wlan0");
+        assert_eq!((networks[1].1.rx, networks[1].1.tx), (0.0, 0.0));
+    }
+
+    #[test]
+    fn repeated_scrape_counts_traffic_once() {
+        let first = scrape(
+            r#"
+node_memory_MemTotal_bytes 16e9
+node_memory_MemAvailable_bytes 4e9
+node_network_receive_bytes_total{device="eth0"} 1000
+node_network_transmit_bytes_total{device="eth0"} 500
+"#,
+        );
+        let mut second = scrape(
+            r#"
+node_memory_MemTotal_bytes 16e9
+node_memory_MemAvailable_bytes 6e9
+node_network_receive_bytes_total{device="eth0"} 3000
+node_network_transmit_bytes_total{device="eth0"} 600
+"#,
+        );
+        second.at = first.at + Duration::from_secs(1);
+        let latest = Arc::new(Mutex::new(Some(first)));
+        let mut source = PrometheusSource::new(latest.clone());
+        let mut system = System::new();
+
+        assert!(source.refresh(&mut system).networks.is_none());
+        *latest.lock() = Some(second);
+        let snapshot = source.refresh(&mut system);
+        let networks = snapshot.networks.unwrap();
+        assert_eq!((networks[0].1.rx, networks[0].1.tx), (2000.0, 100.0));
+
+        // the scrape thread is slower than the refresh
+        let snapshot = source.refresh(&mut system);
+        assert_eq!(snapshot.ram_used, Some(10e9));
+        let networks = snapshot.networks.unwrap();
+        assert_eq!(networks[0].0, "eth0");
+        assert_eq!((networks[0].1.rx, networks[0].1.tx), (0.0, 0.0));
+    }
+
+    #[test]
+    fn parses_the_exposition_format() {
+        let samples = parse_exposition(
+            r#"
+# HELP node_load1 1m load average.
+# TYPE node_load1 gauge
+node_load1 0.42
+node_uname_info{machine="x86_64",nodename="a \"quoted\" name",} 1
+node_cpu_seconds_total{cpu="0", mode="idle"} 1234.5 1700000000000
+node_hwmon_temp_celsius{chip="platform_coretemp_0",sensor="temp1"} NaN
+node_scrape_limit +Inf
+not a sample
+"#,
+        );
+        let labels = |pairs: &[(&str, &str)]| {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect_vec()
+        };
+        assert_eq!(
+            samples,
+            [
+                Sample {
+                    name: "node_load1".to_string(),
+                    labels: vec![],
+                    value: 0.42,
+                },
+                Sample {
+                    name: "node_uname_info".to_string(),
+                    labels: labels(&[("machine", "x86_64"), ("nodename", r#"a "quoted" name"#)]),
+                    value: 1.0,
+                },
+                Sample {
+                    name: "node_cpu_seconds_total".to_string(),
+                    labels: labels(&[("cpu", "0"), ("mode", "idle")]),
+                    value: 1234.5,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn temps_by_label() {
+        let current = scrape(
+            r#"
+node_hwmon_chip_names{chip="platform_coretemp_0",chip_name="coretemp"} 1
+node_hwmon_chip_names{chip="nvme_nvme0",chip_name="nvme"} 1
+node_hwmon_sensor_label{chip="platform_coretemp_0",label="Package id 0",sensor="temp1"} 1
+node_hwmon_sensor_label{chip="platform_coretemp_0",label="Core 1",sensor="temp3"} 1
+node_hwmon_sensor_label{chip="platform_coretemp_0",label="Core 0",sensor="temp2"} 1
+node_hwmon_temp_celsius{chip="platform_coretemp_0",sensor="temp1"} 55
+node_hwmon_temp_celsius{chip="platform_coretemp_0",sensor="temp2"} 50
+node_hwmon_temp_celsius{chip="platform_coretemp_0",sensor="temp3"} 52
+node_hwmon_temp_celsius{chip="nvme_nvme0",sensor="temp1"} 70
+"#,
+        );
+        assert_eq!(
+            read_temps(&current),
+            (Some(vec![(0, 50.0), (1, 52.0)]), Some(55.0))
+        );
+    }
+
+    #[test]
+    fn unlabeled_temps_fall_back_to_the_hottest_cpu_sensor() {
+        let current = scrape(
+            r#"
+node_hwmon_chip_names{chip="pci0000:00_0000:00:18_3",chip_name="k10temp"} 1
+node_hwmon_chip_names{chip="nvme_nvme0",chip_name="nvme"} 1
+node_hwmon_temp_celsius{chip="pci0000:00_0000:00:18_3",sensor="temp1"} 60
+node_hwmon_temp_celsius{chip="pci0000:00_0000:00:18_3",sensor="temp3"} 65
+node_hwmon_temp_celsius{chip="pci0000:00_0000:00:18_3",sensor="temp5"} NaN
+node_hwmon_temp_celsius{chip="nvme_nvme0",sensor="temp1"} 70
+"#,
+        );
+        assert_eq!(read_temps(&current), (None, Some(65.0)));
+        assert_eq!(read_temps(&scrape("")), (None, None));
+    }
+
+    #[test]
+    fn core_usage_from_mode_seconds() {
+        let previous = scrape(
+            r#"
+node_cpu_seconds_total{cpu="0",mode="idle"} 100
+node_cpu_seconds_total{cpu="0",mode="user"} 100
+node_cpu_seconds_total{cpu="1",mode="idle"} 100
+node_cpu_seconds_total{cpu="1",mode="iowait"} 0
+node_cpu_seconds_total{cpu="1",mode="system"} 0
+"#,
+        );
+        let current = scrape(
+            r#"
+node_cpu_seconds_total{cpu="0",mode="idle"} 150
+node_cpu_seconds_total{cpu="0",mode="user"} 150
+node_cpu_seconds_total{cpu="1",mode="idle"} 180
+node_cpu_seconds_total{cpu="1",mode="iowait"} 10
+node_cpu_seconds_total{cpu="1",mode="system"} 10
+node_cpu_seconds_total{cpu="2",mode="idle"} 5
+"#,
+        );
+        let usage = read_core_usage(&previous, &current);
+        assert_eq!(usage.len(), 3);
+        assert!((usage[0] - 50.0).abs() < 1e-4);
+        // iowait counts as idle
+        assert!((usage[1] - 10.0).abs() < 1e-4);
+        // new core, nothing to compare to: all of its time is new and idle
+        assert_eq!(usage[2], 0.0);
+    }
+
+    #[test]
+    fn drives_without_pseudo_filesystems() {
+        let current = scrape(
+            r#"
+node_filesystem_size_bytes{device="/dev/sda2",fstype="ext4",mountpoint="/"} 500
+node_filesystem_size_bytes{device="/dev/sda2",fstype="ext4",mountpoint="/"} 500
+node_filesystem_avail_bytes{device="/dev/sda2",fstype="ext4",mountpoint="/"} 200
+node_filesystem_size_bytes{device="/dev/sda1",fstype="vfat",mountpoint="/boot"} 100
+node_filesystem_avail_bytes{device="/dev/sda1",fstype="vfat",mountpoint="/boot"} 90
+node_filesystem_size_bytes{device="tmpfs",fstype="tmpfs",mountpoint="/run"} 50
+node_filesystem_avail_bytes{device="tmpfs",fstype="tmpfs",mountpoint="/run"} 50
+node_filesystem_size_bytes{device="/dev/sdb1",fstype="ext4",mountpoint="/data"} 700
+"#,
+        );
+        let drives = read_drives(&current).unwrap();
+        let drives = drives
+            .iter()
+            .map(|d| (d.mount.as_str(), d.total_space, d.available_space))
+            .collect_vec();
+        assert_eq!(drives, [("/", 500, 200), ("/boot", 100, 90)]);
+        assert!(read_drives(&scrape("")).is_none());
+    }
+}
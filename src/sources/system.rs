@@ -0,0 +1,55 @@
+use itertools::Itertools;
+use sysinfo::{CpuExt, CpuRefreshKind, DiskExt, NetworkExt, NetworksExt, System, SystemExt};
+
+use super::{DriveData, MetricSnapshot, MetricSource, NetworkData};
+
+/// CPU usage, memory, drive space and network traffic from sysinfo.
+pub struct SysinfoSource;
+
+impl MetricSource for SysinfoSource {
+    fn name(&self) -> &'static str {
+        "sysinfo"
+    }
+
+    fn refresh(&mut self, system: &mut System) -> MetricSnapshot {
+        system.refresh_cpu_specifics(CpuRefreshKind::new().with_cpu_usage());
+        system.refresh_disks();
+        system.refresh_networks();
+        system.refresh_memory();
+
+        let drives = system
+            .disks()
+            .iter()
+            .map(|d| DriveData {
+                mount: d.mount_point().to_str().unwrap().replace('\\', ""),
+                total_space: d.total_space(),
+                available_space: d.available_space(),
+                ..Default::default()
+            })
+            .collect_vec();
+
+        let networks = system
+            .networks()
+            .iter()
+            .map(|(n, d)| {
+                (
+                    n.to_string(),
+                    NetworkData {
+                        tx: d.transmitted() as f64,
+                        rx: d.received() as f64,
+                    },
+                )
+            })
+            .collect_vec();
+
+        MetricSnapshot {
+            cpu_usage: Some(system.global_cpu_info().cpu_usage()),
+            core_usage: Some(system.cpus().iter().map(|c| c.cpu_usage()).collect_vec()),
+            ram_used: Some(system.used_memory() as f32),
+            ram_total: Some(system.total_memory() as f32),
+            drives: Some(drives),
+            networks: Some(networks),
+            ..Default::default()
+        }
+    }
+}
@@ -3,10 +3,13 @@ use crate::{
     circlevec::CircleVec,
     color::{auto_color_dark, get_base_background},
     components::edgy_progress::EdgyProgressBar,
-    ohw::MyNode,
-    process::{add_english_counter, get_pdh_process_data, init_process_metrics, Process},
-    sidebar::STATIC_HWND,
-    step_timing, CurrentStep, MyApp, SIDEBAR_WIDTH,
+    ping::PingStats,
+    platform::{accent_color, open_task_manager},
+    process::Process,
+    sources::{BatteryStatus, GpuData, MetricSnapshot, NetworkData},
+    step_timing,
+    tiles::{refresh_tiles, show_tiles},
+    CurrentStep, MyApp, SIDEBAR_WIDTH,
 };
 use chrono::{Local, Timelike};
 use eframe::{
@@ -19,21 +22,8 @@ use eframe::{
 };
 use egui_extras::{Column, TableBuilder};
 use itertools::Itertools;
-use nvml_wrapper::enum_wrappers::device::Clock;
-use sysinfo::{CpuExt, CpuRefreshKind, DiskExt, NetworkExt, NetworksExt, SystemExt};
-use tokio::process::Command;
-use windows::{
-    core::PWSTR,
-    w,
-    Win32::{
-        Foundation::BOOL,
-        Graphics::Dwm::DwmGetColorizationColor,
-        System::Performance::{
-            PdhBrowseCountersW, PdhCollectQueryData, PdhGetFormattedCounterValue,
-            PDH_BROWSE_DLG_CONFIG_W, PDH_FMT_DOUBLE, PERF_DETAIL_WIZARD,
-        },
-    },
-};
+use std::sync::Arc;
+use sysinfo::SystemExt;
 
 pub fn set_system_info_components(appdata: &mut MyApp, ui: &mut Ui) {
     step_timing(appdata, crate::CurrentStep::Begin);
@@ -45,6 +35,7 @@ pub fn set_system_info_components(appdata: &mut MyApp, ui: &mut Ui) {
     show_ping(appdata, ui);
     show_processes(appdata, ui);
     show_battery(appdata, ui);
+    show_tiles(appdata, ui);
 }
 
 fn show_network(appdata: &mut MyApp, ui: &mut Ui) {
@@ -123,10 +114,9 @@ fn show_network(appdata: &mut MyApp, ui: &mut Ui) {
     step_timing(appdata, crate::CurrentStep::Network);
 }
 
-fn filter_networks(appdata: &mut MyApp) -> Vec<(String, MyNetworkData)> {
+fn filter_networks(appdata: &mut MyApp) -> Vec<(String, NetworkData)> {
     appdata
-        .system_status
-        .networks()
+        .networks
         .iter()
         .filter(|i| {
             *appdata
@@ -137,136 +127,17 @@ fn filter_networks(appdata: &mut MyApp) -> Vec<(String, MyNetworkData)> {
                 .entry(i.0.to_string())
                 .or_default()
         })
-        .map(|(n, d)| {
-            (
-                n.to_string(),
-                MyNetworkData {
-                    tx: d.transmitted() as f64,
-                    rx: d.received() as f64,
-                },
-            )
-        })
+        .cloned()
         .collect_vec()
 }
 
-#[derive(Default, Debug, Clone)]
-#[allow(dead_code)]
-pub struct GpuData {
-    utilization: f64,
-    temperature: f32,
-    memory_free: f32,
-    memory_used: f32,
-    memory_total: f32,
-    power_usage: f32,
-    power_limit: f32,
-    fan_percentage: f32,
-    clock_mhz: f32,
-    max_clock: f32,
-}
-
-fn timing_to_str(timestamp: std::time::Instant, text: &mut String, perf_trace: bool) {
-    if perf_trace {
-        *text += &format!("{}\n", timestamp.elapsed().as_micros());
-    }
-}
-
-pub fn refresh_gpu(appdata: &mut MyApp) {
-    let perf_trace = appdata.settings.lock().current_settings.track_timings;
-    step_timing(appdata, CurrentStep::UpdateGPU);
-    if let Some(gpu) = appdata.nvid_info.as_ref() {
-        let mut text = String::new();
-        timing_to_str(appdata.current_frame_start, &mut text, perf_trace); // , 96
-
-        let mut utilization = 0.0;
-        let mut temperature = 0.0;
-        let mut fan_percentage = 0.0;
-        let mut power_usage = 0.0;
-        let mut memory_free = 0.0;
-        let mut memory_used = 0.0;
-        let mut memory_total = 0.0;
-        let mut clock_mhz = 0.0;
-
-        let power_limit;
-        let max_clock;
-        if let Some(gpu) = &appdata.gpu {
-            power_limit = gpu.power_limit;
-            max_clock = gpu.max_clock;
-        } else {
-            let gpu = gpu.device_by_index(0).unwrap();
-            power_limit = gpu.enforced_power_limit().unwrap() as f32 / 1000.0;
-            max_clock = gpu.max_clock_info(Clock::Graphics).unwrap_or_default() as f32;
-        }
-        timing_to_str(appdata.current_frame_start, &mut text, perf_trace);
-
-        let ohw = appdata.ohw_info.lock();
-        let n = ohw.select("#0|+images_icon/nvidia.png");
-        if let Some(n) = n {
-            timing_to_str(appdata.current_frame_start, &mut text, perf_trace);
-
-            temperature = n.parse_value_path_def("Temperatures|#0");
-            timing_to_str(appdata.current_frame_start, &mut text, perf_trace);
-
-            power_usage = n.parse_value_path_def("Powers|#0");
-            timing_to_str(appdata.current_frame_start, &mut text, perf_trace);
-
-            memory_free = n.parse_value_path_def::<f32>("Data|#0") * 1024.0 * 1024.0;
-            timing_to_str(appdata.current_frame_start, &mut text, perf_trace);
-
-            memory_used = n.parse_value_path_def::<f32>("Data|#1") * 1024.0 * 1024.0;
-            timing_to_str(appdata.current_frame_start, &mut text, perf_trace);
-
-            memory_total = n.parse_value_path_def::<f32>("Data|#2") * 1024.0 * 1024.0;
-            timing_to_str(appdata.current_frame_start, &mut text, perf_trace);
-
-            fan_percentage = n.parse_value_path_def("Controls|#0");
-            timing_to_str(appdata.current_frame_start, &mut text, perf_trace);
-
-            utilization = n.parse_value_path_def("Load|#0");
-            timing_to_str(appdata.current_frame_start, &mut text, perf_trace);
-
-            clock_mhz = n.parse_value_path_def("Clocks|#0");
-            timing_to_str(appdata.current_frame_start, &mut text, perf_trace);
-        };
-        drop(ohw);
-
-        let g = GpuData {
-            utilization,
-            temperature,
-            memory_free,
-            memory_used,
-            memory_total,
-            power_usage,
-            power_limit,
-            fan_percentage,
-            clock_mhz,
-            max_clock,
-        };
-        timing_to_str(appdata.current_frame_start, &mut text, perf_trace);
-
-        appdata.gpu_buffer.add(g.utilization);
-        appdata
-            .gpu_mem_buffer
-            .add((g.memory_used / g.memory_total) as f64);
-        appdata
-            .gpu_power_buffer
-            .add((g.power_usage / g.power_limit) as f64);
-        appdata.gpu_temp_buffer.add((g.temperature) as f64);
-
-        if perf_trace && appdata.framecount < 1000 {
-            println!("{text}");
-        }
-        appdata.gpu = Some(g);
-        step_timing(appdata, CurrentStep::UpdateGPU);
-    }
-}
-
 fn show_processes(appdata: &mut MyApp, ui: &mut Ui) {
     ui.vertical_centered(|ui| ui.label("Processes"));
     // By CPU
     let mut p = appdata.processes.clone();
 
     p.sort_unstable_by(|a, b| b.cpu.total_cmp(&a.cpu));
-    let cpu_count = appdata.system_status.cpus().len();
+    let cpu_count = appdata.core_usage.len().max(1);
     add_process_table(
         ui,
         5,
@@ -279,7 +150,7 @@ fn show_processes(appdata: &mut MyApp, ui: &mut Ui) {
 
     // By Memory
     let mut p = appdata.processes.clone();
-    p.sort_unstable_by(|a, b| b.memory.cmp(&a.memory));
+    p.sort_unstable_by_key(|p| std::cmp::Reverse(p.memory));
     add_process_table(
         ui,
         5,
@@ -293,23 +164,57 @@ fn show_processes(appdata: &mut MyApp, ui: &mut Ui) {
 
 fn show_ping(appdata: &mut MyApp, ui: &mut Ui) {
     ui.vertical_centered(|ui| ui.label("Ping"));
-    let pings = appdata.ping_buffer.read();
-    let last_ping = pings.last().copied().unwrap_or_default();
-    let max_ping = pings.iter().max().copied().unwrap_or_default();
-    let line = Line::new(
-        (0..appdata.ping_buffer.capacity())
-            .map(|i| [i as f64, { pings[i] as f64 }])
-            .collect::<PlotPoints>(),
-    );
-
-    let lp_str = if last_ping == 0 {
-        "ERR".to_string()
-    } else {
-        format!("{last_ping:.0} ms")
-    };
+    let histories = appdata.pings.lock();
+    let mut lines = vec![];
+    let mut max_y = 50.0f64;
+    for (i, history) in histories.iter().enumerate() {
+        let pings = history.buffer.read();
+        let last = pings.last().copied().unwrap_or_default();
+        let stats = PingStats::of(&pings);
+        let max_ping = stats.max.unwrap_or_default();
+        max_y = max_y.max(max_ping);
+
+        // failed pings are drawn at 0, like before
+        let mut line = Line::new(
+            (0..history.buffer.capacity())
+                .map(|i| [i as f64, pings[i].rtt().unwrap_or_default()])
+                .collect::<PlotPoints>(),
+        );
+        let label = match history.target.label.as_str() {
+            l if histories.len() > 1 && !l.is_empty() => format!("{l} "),
+            _ => String::new(),
+        };
+        let mut text =
+            RichText::new(format!("{label}M: {max_ping:.0}ms, C: {}", last.label())).size(12.0);
+        let ms = |v: Option<f64>| {
+            v.map(|v| format!("{v:.0}"))
+                .unwrap_or_else(|| "-".to_string())
+        };
+        let mut details = RichText::new(format!(
+            "loss {:.0}%, jitter {}ms, p50 {} p95 {}ms",
+            stats.loss.unwrap_or_default(),
+            stats
+                .jitter
+                .map(|j| format!("{j:.1}"))
+                .unwrap_or_else(|| "-".to_string()),
+            ms(stats.p50),
+            ms(stats.p95),
+        ))
+        .small();
+        // one target looks like it always did, several need telling apart
+        if histories.len() > 1 {
+            let color = auto_color_dark(i as i32);
+            line = line.color(color);
+            text = text.color(color);
+            details = details.color(color);
+        }
+        ui.label(text);
+        ui.label(details);
+        lines.push(line);
+    }
+    drop(histories);
 
-    ui.label(RichText::new(format!("M: {max_ping:.0}ms, C: {lp_str}")).size(12.0));
-    add_graph("ping", ui, vec![line], &[50.0, max_ping as f64]);
+    add_graph("ping", ui, lines, &[max_y]);
     step_timing(appdata, crate::CurrentStep::Ping);
     ui.separator();
 }
@@ -319,6 +224,31 @@ fn show_battery(appdata: &mut MyApp, ui: &mut Ui) {
         return;
     }
     ui.vertical_centered(|ui| ui.label("Battery"));
+    if let Some(battery) = appdata.battery.as_ref() {
+        let status = match battery.status {
+            BatteryStatus::Charging => "⬆",
+            BatteryStatus::Discharging => "⬇",
+            BatteryStatus::Full => "Full",
+            BatteryStatus::NotCharging | BatteryStatus::Unknown => "",
+        };
+        let power = battery
+            .power
+            .map(|p| format!(" {:.1}W", p.abs()))
+            .unwrap_or_default();
+        ui.label(RichText::new(format!("{:.0}% {status}{power}", battery.level)).size(12.0));
+        if let Some(capacity) = battery.capacity.as_ref() {
+            ui.label(
+                RichText::new(format!(
+                    "Cap: {:.1} / {:.1}{} ({:.0}%)",
+                    capacity.full,
+                    capacity.design,
+                    capacity.unit,
+                    capacity.health()
+                ))
+                .small(),
+            );
+        }
+    }
     let level = appdata.battery_level_buffer.read();
     let level_line = Line::new(
         (0..appdata.battery_level_buffer.capacity())
@@ -344,36 +274,9 @@ fn show_battery(appdata: &mut MyApp, ui: &mut Ui) {
 fn show_cpu(appdata: &mut MyApp, ui: &mut Ui) {
     ui.vertical_centered(|ui| ui.label("CPU"));
 
-    let ohw_opt = appdata.ohw_info.lock();
-    let temps_node = ohw_opt.select("#0|+images_icon/cpu.png|Temperatures");
-    let coretemps = if let Some(ohw) = temps_node {
-        ohw.Children
-            .iter()
-            .filter_map(|n| {
-                if let Ok(text) = n.Text.replace("CPU Core #", "").parse::<i32>() {
-                    Some((
-                        text,
-                        n.Value
-                            .replace("°C", "")
-                            .replace(',', ".")
-                            .trim()
-                            .parse::<f32>()
-                            .unwrap_or_default(),
-                    ))
-                } else {
-                    None
-                }
-            })
-            .collect_vec()
-    } else {
-        vec![]
-    };
-
     let max_temp_line = appdata.cpu_maxtemp_buffer.read();
     let max_temp = max_temp_line.last().copied().unwrap_or_default();
 
-    drop(ohw_opt);
-
     step_timing(appdata, crate::CurrentStep::CpuCrunch);
     ui.spacing_mut().interact_size = [15.0, 12.0].into();
 
@@ -418,7 +321,9 @@ fn show_cpu(appdata: &mut MyApp, ui: &mut Ui) {
     );
     let power = appdata.cpu_power_buffer.read();
     let current_power = power.last().copied().unwrap_or_default();
-    let max_power = appdata.settings.lock().current_settings.max_cpu_power;
+    let max_power = appdata
+        .cpu_power_limit
+        .unwrap_or_else(|| appdata.settings.lock().current_settings.max_cpu_power);
 
     ui.add(
         EdgyProgressBar::new((current_power / max_power) as f32)
@@ -435,10 +340,9 @@ fn show_cpu(appdata: &mut MyApp, ui: &mut Ui) {
         .spacing([2.0, 2.0])
         .striped(true)
         .show(ui, |ui| {
-            for (i, cpu_chunk) in appdata.system_status.cpus().chunks(2).enumerate() {
-                for cpu in cpu_chunk {
-                    let temp = coretemps.get(i).map(|o| o.1).unwrap_or_default();
-                    let usage = cpu.cpu_usage();
+            for (i, cpu_chunk) in appdata.core_usage.chunks(2).enumerate() {
+                for &usage in cpu_chunk {
+                    let temp = appdata.core_temps.get(i).map(|o| o.1).unwrap_or_default();
                     ui.add(
                         EdgyProgressBar::new(usage / 100.0)
                             .desired_width(SIDEBAR_WIDTH / 2.0 - 5.0)
@@ -491,36 +395,36 @@ fn show_cpu(appdata: &mut MyApp, ui: &mut Ui) {
 }
 
 fn show_gpu(appdata: &MyApp, ui: &mut Ui) {
-    if appdata.nvid_info.is_some() {
-        ui.vertical_centered(|ui| ui.label("GPU"));
+    for (i, state) in appdata.gpus.iter().enumerate() {
+        let gpu = &state.data;
+        let title = if gpu.name.is_empty() {
+            format!("GPU {i}")
+        } else {
+            gpu.name.clone()
+        };
+        ui.vertical_centered(|ui| ui.add(Label::new(title).wrap(false)));
 
-        Grid::new("gpu_grid_upper")
+        Grid::new(("gpu_grid_upper", i))
             .num_columns(2)
             .spacing([2.0, 2.0])
             .striped(true)
             .show(ui, |ui| {
                 ui.add(
-                    EdgyProgressBar::new(appdata.gpu.as_ref().unwrap().utilization as f32 / 100.0)
+                    EdgyProgressBar::new(gpu.utilization as f32 / 100.0)
                         .text(
-                            RichText::new(format!(
-                                "GPU: {:.1}%",
-                                appdata.gpu.as_ref().unwrap().utilization
-                            ))
-                            .small()
-                            .strong(),
+                            RichText::new(format!("GPU: {:.1}%", gpu.utilization))
+                                .small()
+                                .strong(),
                         )
                         .desired_width(SIDEBAR_WIDTH / 2.0 - 5.0)
                         .fill(auto_color_dark(0)),
                 );
                 ui.add(
-                    EdgyProgressBar::new(appdata.gpu.as_ref().unwrap().temperature / 100.0)
+                    EdgyProgressBar::new(gpu.temperature / 100.0)
                         .text(
-                            RichText::new(format!(
-                                "{:.0} °C",
-                                appdata.gpu.as_ref().unwrap().temperature
-                            ))
-                            .small()
-                            .strong(),
+                            RichText::new(format!("{:.0} °C", gpu.temperature))
+                                .small()
+                                .strong(),
                         )
                         .desired_width(SIDEBAR_WIDTH / 2.0 - 5.0)
                         .fill(auto_color_dark(3)),
@@ -528,84 +432,72 @@ fn show_gpu(appdata: &MyApp, ui: &mut Ui) {
             });
 
         ui.add(
-            EdgyProgressBar::new(
-                appdata.gpu.as_ref().unwrap().memory_used
-                    / appdata.gpu.as_ref().unwrap().memory_total,
-            )
-            .text(
-                RichText::new(format!(
-                    "Mem: {} / {}",
-                    format_bytes(appdata.gpu.as_ref().unwrap().memory_used as f64),
-                    format_bytes(appdata.gpu.as_ref().unwrap().memory_total as f64)
-                ))
-                .small()
-                .strong(),
-            )
-            .fill(auto_color_dark(1)),
+            EdgyProgressBar::new(gpu.memory_used / gpu.memory_total.max(0.01))
+                .text(
+                    RichText::new(format!(
+                        "Mem: {} / {}",
+                        format_bytes(gpu.memory_used as f64),
+                        format_bytes(gpu.memory_total as f64)
+                    ))
+                    .small()
+                    .strong(),
+                )
+                .fill(auto_color_dark(1)),
         );
 
         ui.add(
-            EdgyProgressBar::new(
-                appdata.gpu.as_ref().unwrap().power_usage
-                    / appdata.gpu.as_ref().unwrap().power_limit,
-            )
-            .text(
-                RichText::new(format!(
-                    "Pow: {:.0}W / {:.0}W",
-                    appdata.gpu.as_ref().unwrap().power_usage,
-                    appdata.gpu.as_ref().unwrap().power_limit
-                ))
-                .small()
-                .strong(),
-            )
-            .fill(auto_color_dark(2)),
+            EdgyProgressBar::new(gpu.power_usage / gpu.power_limit.max(0.01))
+                .text(
+                    RichText::new(format!(
+                        "Pow: {:.0}W / {:.0}W",
+                        gpu.power_usage, gpu.power_limit
+                    ))
+                    .small()
+                    .strong(),
+                )
+                .fill(auto_color_dark(2)),
         );
         ui.add(
-            EdgyProgressBar::new(
-                appdata.gpu.as_ref().unwrap().clock_mhz
-                    / appdata.gpu.as_ref().unwrap().max_clock.max(0.01),
-            )
-            .text(
+            EdgyProgressBar::new(gpu.clock_mhz / gpu.max_clock.max(0.01)).text(
                 RichText::new(format!(
                     "Clk: {:.0}MHz / {:.0}MHz",
-                    appdata.gpu.as_ref().unwrap().clock_mhz,
-                    appdata.gpu.as_ref().unwrap().max_clock
+                    gpu.clock_mhz, gpu.max_clock
                 ))
                 .small()
                 .strong(),
             ),
         );
 
-        let gpu_buf = appdata.gpu_buffer.read();
+        let gpu_buf = state.buffer.read();
         let gpu_line = Line::new(
-            (0..appdata.gpu_buffer.capacity())
+            (0..state.buffer.capacity())
                 .map(|i| [i as f64, { gpu_buf[i] }])
                 .collect::<PlotPoints>(),
         );
 
-        let mem_buf = appdata.gpu_mem_buffer.read();
+        let mem_buf = state.mem_buffer.read();
         let mem_line = Line::new(
-            (0..appdata.gpu_mem_buffer.capacity())
+            (0..state.mem_buffer.capacity())
                 .map(|i| [i as f64, { mem_buf[i] * 100.0 }])
                 .collect::<PlotPoints>(),
         );
 
-        let temp_buf = appdata.gpu_temp_buffer.read();
+        let temp_buf = state.temp_buffer.read();
         let temp_line = Line::new(
-            (0..appdata.gpu_temp_buffer.capacity())
+            (0..state.temp_buffer.capacity())
                 .map(|i| [i as f64, { temp_buf[i] }])
                 .collect::<PlotPoints>(),
         );
 
-        let pow_buf = appdata.gpu_power_buffer.read();
+        let pow_buf = state.power_buffer.read();
         let pow_line = Line::new(
-            (0..appdata.gpu_power_buffer.capacity())
+            (0..state.power_buffer.capacity())
                 .map(|i| [i as f64, { pow_buf[i] * 100.0 }])
                 .collect::<PlotPoints>(),
         );
 
         add_graph(
-            "gpu",
+            &format!("gpu {i}"),
             ui,
             vec![gpu_line, mem_line, pow_line, temp_line],
             &[100.0],
@@ -761,17 +653,11 @@ fn add_process_table(
     ui.separator();
 
     if clicked {
-        match Command::new("powershell")
-            .args(["start", "taskmgr", "-v runAs"])
-            .spawn()
-        {
-            Ok(_c) => println!("Starting Task Manager"),
-            Err(e) => println!("{e}"),
-        };
+        open_task_manager();
     }
 }
 
-fn add_graph(id: &str, ui: &mut Ui, line: Vec<Line>, max_y: &[f64]) {
+pub(crate) fn add_graph(id: &str, ui: &mut Ui, line: Vec<Line>, max_y: &[f64]) {
     let mut p = Plot::new(id)
         .show_axes([true, true])
         .label_formatter(|_, _| "".to_string())
@@ -805,29 +691,33 @@ fn show_drives(appdata: &MyApp, ui: &mut Ui) {
         .striped(true)
         .show(ui, |ui| {
             for (i, d) in appdata
-                .system_status
-                .disks()
+                .drives
                 .iter()
-                .sorted_by_key(|d| d.mount_point())
+                .sorted_by(|a, b| a.mount.cmp(&b.mount))
                 .enumerate()
             {
                 ui.spacing_mut().interact_size = [15.0, 12.0].into();
-                let mount = d.mount_point().to_str().unwrap().replace('\\', "");
-                let (_, _, value) = appdata
-                    .disk_time_value_handle_map
-                    .iter()
-                    .find(|(s, _, _)| s == &mount)
-                    .unwrap();
-
-                ui.add(Label::new(
+                let mount = &d.mount;
+                let value = d.busy.unwrap_or_default();
+
+                let label = ui.add(Label::new(
                     RichText::new(format!("{mount} {value:.1}%"))
                         .small()
                         .strong(),
                 ));
+                if let (Some(read), Some(write)) = (d.read_bytes_per_sec, d.write_bytes_per_sec) {
+                    label.on_hover_text(format!(
+                        "R: {}/s\nW: {}/s\nIOPS: {:.0}",
+                        format_bytes(read),
+                        format_bytes(write),
+                        d.iops.unwrap_or_default()
+                    ));
+                }
                 ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
                     ui.add(
                         EdgyProgressBar::new(
-                            (d.total_space() - d.available_space()) as f32 / d.total_space() as f32,
+                            (d.total_space - d.available_space) as f32
+                                / d.total_space.max(1) as f32,
                         )
                         .desired_width(
                             appdata.settings.lock().current_settings.location.width * 0.55,
@@ -835,7 +725,7 @@ fn show_drives(appdata: &MyApp, ui: &mut Ui) {
                         .text(
                             RichText::new(format!(
                                 "Free: {}",
-                                format_bytes(d.available_space() as f64),
+                                format_bytes(d.available_space as f64),
                             ))
                             .small()
                             .strong(),
@@ -863,140 +753,97 @@ fn show_drives(appdata: &MyApp, ui: &mut Ui) {
     ui.separator();
 }
 
-fn refresh_disk_io_time(appdata: &mut MyApp) {
-    unsafe {
-        // Siehe: https://learn.microsoft.com/en-us/windows/win32/perfctrs/pdh-error-codes
-        for (d, handle, value) in &mut appdata.disk_time_value_handle_map {
-            let mut new_value = Default::default();
-            PdhGetFormattedCounterValue(*handle, PDH_FMT_DOUBLE, None, &mut new_value);
-            *value = new_value.Anonymous.doubleValue;
-            appdata
-                .disk_buffer
-                .entry(d.clone())
-                .or_insert(CircleVec::new())
-                .add(*value);
+fn refresh_drives(appdata: &mut MyApp, snapshot: &mut MetricSnapshot) {
+    if let Some(drives) = snapshot.drives.take() {
+        for d in &drives {
+            if let Some(busy) = d.busy {
+                appdata
+                    .disk_buffer
+                    .entry(d.mount.clone())
+                    .or_insert(CircleVec::new())
+                    .add(busy);
+            }
         }
+        appdata.drives = drives;
     }
 }
 
 pub fn init_system(appdata: &mut MyApp) {
     // open_performance_browser();
 
-    appdata.process_metric_handles = init_process_metrics(appdata.windows_performance_query_handle);
     appdata.system_status.refresh_disks_list();
     appdata.system_status.refresh_cpu();
 
-    // iterate over disks and add disk io time counters
-    for d in appdata
-        .system_status
-        .disks()
-        .iter()
-        .sorted_by_key(|d| d.mount_point())
-    {
-        let drive_letter = d.mount_point().to_str().unwrap().replace('\\', "");
-        let metric_handle = add_english_counter(
-            format!(r"\LogicalDisk({drive_letter})\% Disk Time"),
-            appdata.windows_performance_query_handle,
-        );
-
-        appdata
-            .disk_time_value_handle_map
-            .push((drive_letter, metric_handle, 0.0));
+    for source in appdata.sources.iter_mut() {
+        source.init(&mut appdata.system_status);
     }
-
-    unsafe { PdhCollectQueryData(appdata.windows_performance_query_handle) };
 }
 
-pub fn get_windows_glass_color(use_plain_blackground: bool) -> Color32 {
+pub fn get_glass_color(use_plain_blackground: bool) -> Color32 {
     if use_plain_blackground {
         return get_base_background();
     }
-    let mut col: u32 = 0;
-    let mut opaque: BOOL = BOOL(0);
-    unsafe {
-        DwmGetColorizationColor(&mut col, &mut opaque).unwrap();
-    }
-    let bytes: [u8; 4] = col.to_be_bytes();
-    Color32::from_rgba_premultiplied(
-        darken(bytes[1]),
-        darken(bytes[2]),
-        darken(bytes[3]),
-        bytes[0],
-    )
+    let Some([a, r, g, b]) = accent_color() else {
+        return get_base_background();
+    };
+    Color32::from_rgba_premultiplied(darken(r), darken(g), darken(b), a)
 }
 
 fn darken(v: u8) -> u8 {
     (v as f32 * 0.4) as u8
 }
 
-#[allow(dead_code)]
-pub fn open_performance_browser() {
-    unsafe {
-        let hwnd = *STATIC_HWND.read().unwrap();
-        let mut buf: [u16; 10000] = [0; 10000];
-        let returnpathbuffer = PWSTR::from_raw(&mut buf as *mut u16);
-        let p = PWSTR::from_raw(w!("hello").as_ptr() as *mut _);
-        PdhBrowseCountersW(&PDH_BROWSE_DLG_CONFIG_W {
-            _bitfield: 0,
-            hWndOwner: hwnd,
-            szDataSource: PWSTR::null(),
-            szReturnPathBuffer: returnpathbuffer,
-            cchReturnPathLength: 10000,
-            pCallBack: None,
-            dwCallBackArg: 0,
-            CallBackStatus: 0,
-            dwDefaultDetailLevel: PERF_DETAIL_WIZARD,
-            szDialogBoxCaption: p,
-        } as *const PDH_BROWSE_DLG_CONFIG_W);
-
-        println!("{}", returnpathbuffer.display());
+pub fn refresh(appdata: &mut MyApp) {
+    let mut snapshot = MetricSnapshot::default();
+    for i in 0..appdata.sources.len() {
+        let source = &mut appdata.sources[i];
+        let name = source.name();
+        snapshot.merge(source.refresh(&mut appdata.system_status));
+        step_timing(appdata, CurrentStep::UpdateSource(name));
     }
-}
 
-// fn convert_to_pcwstr(s: &str) -> PCWSTR {
-//     let mut v = s.encode_utf16().collect_vec();
-//     v.push(0);
-//     let p = v.as_ptr();
-//     PCWSTR::from_raw(p)
-// }
+    refresh_tiles(appdata, &snapshot);
+    step_timing(appdata, CurrentStep::UpdateTiles);
 
-pub fn refresh(appdata: &mut MyApp) {
-    // refresh windows perfcount stats once
-    unsafe { PdhCollectQueryData(appdata.windows_performance_query_handle) };
+    if let Some(recorder) = appdata.recorder.as_mut() {
+        if let Err(e) = recorder.write(&appdata.ohw_info.lock(), &snapshot) {
+            println!("Recording {}: {e}", recorder.path().display());
+            appdata.recorder = None;
+        }
+    }
 
-    refresh_cpu(appdata);
+    refresh_cpu(appdata, &mut snapshot);
     step_timing(appdata, CurrentStep::UpdateCPU);
 
-    refresh_gpu(appdata);
+    refresh_gpu(appdata, &mut snapshot);
     step_timing(appdata, CurrentStep::UpdateGPU);
 
-    appdata.system_status.refresh_disks();
-    step_timing(appdata, CurrentStep::UpdateSystemDisk);
-
-    refresh_system_memory(appdata);
+    refresh_system_memory(appdata, &mut snapshot);
     step_timing(appdata, CurrentStep::UpdateSystemMemory);
 
-    refresh_networks(appdata);
+    refresh_networks(appdata, &mut snapshot);
     step_timing(appdata, CurrentStep::UpdateSystemNetwork);
 
-    refresh_disk_io_time(appdata);
+    refresh_drives(appdata, &mut snapshot);
     step_timing(appdata, CurrentStep::UpdateIoTime);
 
-    refresh_processes(appdata);
+    refresh_processes(appdata, &mut snapshot);
     step_timing(appdata, CurrentStep::UpdateSystemProcess);
 
-    refresh_battery(appdata);
+    refresh_battery(appdata, &mut snapshot);
     step_timing(appdata, CurrentStep::UpdateBattery);
 }
 
-fn refresh_processes(appdata: &mut MyApp) {
-    appdata.processes = get_pdh_process_data(&appdata.process_metric_handles);
+fn refresh_processes(appdata: &mut MyApp, snapshot: &mut MetricSnapshot) {
+    if let Some(processes) = snapshot.processes.take() {
+        appdata.processes = processes;
+    }
 }
 
 pub fn refresh_color(appdata: &mut MyApp, ui: &mut Ui) {
     let v = ui.visuals_mut();
     v.override_text_color = Some(Color32::from_gray(250));
-    v.window_fill = get_windows_glass_color(
+    v.window_fill = get_glass_color(
         appdata
             .settings
             .lock()
@@ -1005,13 +852,10 @@ pub fn refresh_color(appdata: &mut MyApp, ui: &mut Ui) {
     );
 }
 
-pub struct MyNetworkData {
-    tx: f64,
-    rx: f64,
-}
-
-fn refresh_networks(appdata: &mut MyApp) {
-    appdata.system_status.refresh_networks();
+fn refresh_networks(appdata: &mut MyApp, snapshot: &mut MetricSnapshot) {
+    if let Some(networks) = snapshot.networks.take() {
+        appdata.networks = networks;
+    }
     for (name, data) in filter_networks(appdata) {
         let e = appdata
             .net_down_buffer
@@ -1026,17 +870,9 @@ fn refresh_networks(appdata: &mut MyApp) {
     }
 }
 
-fn refresh_system_memory(appdata: &mut MyApp) {
-    let ohw = appdata.ohw_info.lock();
-    let mut cur_ram = 0.0;
-    let mut tot_ram = 0.0;
-    if ohw.is_some() {
-        let nodes = ohw.select("#0|Generic Memory|Data").cloned();
-
-        cur_ram = nodes.parse_value_path_def::<f32>("Memory Used") * 1024.0 * 1024.0 * 1024.0;
-        tot_ram = cur_ram
-            + nodes.parse_value_path_def::<f32>("Memory Available") * 1024.0 * 1024.0 * 1024.0;
-    }
+fn refresh_system_memory(appdata: &mut MyApp, snapshot: &mut MetricSnapshot) {
+    let cur_ram = snapshot.ram_used.unwrap_or_default();
+    let tot_ram = snapshot.ram_total.unwrap_or_default();
     appdata.cur_ram = cur_ram;
     if appdata.total_ram == 0.0 {
         appdata.total_ram = tot_ram;
@@ -1044,89 +880,113 @@ fn refresh_system_memory(appdata: &mut MyApp) {
     appdata.ram_buffer.add(cur_ram / appdata.total_ram);
 }
 
-fn refresh_cpu(appdata: &mut MyApp) {
-    appdata
-        .system_status
-        .refresh_cpu_specifics(CpuRefreshKind::new().with_cpu_usage());
+fn refresh_cpu(appdata: &mut MyApp, snapshot: &mut MetricSnapshot) {
     appdata
         .cpu_buffer
-        .add(appdata.system_status.global_cpu_info().cpu_usage());
-
-    let ohw_opt = appdata.ohw_info.lock();
-    let coretemps = if let Some(ohw) = ohw_opt.as_ref() {
-        ohw.Children[0]
-            .Children
-            .iter()
-            .find(|n| n.ImageURL == "images_icon/cpu.png")
-            .unwrap()
-            .Children
-            .iter()
-            .find(|n| n.Text == "Temperatures")
-            .unwrap()
-            .Children
-            .iter()
-            .filter_map(|n| {
-                if n.Text.contains("CPU Core #") {
-                    if let Ok(text) = n.Text.replace("CPU Core #", "").parse::<i32>() {
-                        Some((
-                            text,
-                            n.Value
-                                .replace("°C", "")
-                                .replace(',', ".")
-                                .trim()
-                                .parse::<f32>()
-                                .unwrap_or_default(),
-                        ))
-                    } else {
-                        None
-                    }
-                } else {
-                    None
-                }
-            })
-            .collect_vec()
-    } else {
-        vec![]
-    };
+        .add(snapshot.cpu_usage.unwrap_or_default());
+    if let Some(core_usage) = snapshot.core_usage.take() {
+        appdata.core_usage = core_usage;
+    }
 
-    let max_temp = coretemps
+    appdata.core_temps = snapshot.core_temps.take().unwrap_or_default();
+    let max_temp = appdata
+        .core_temps
         .iter()
         .map(|(_, v)| v)
-        .max_by(|x, y| x.abs().partial_cmp(&y.abs()).unwrap())
-        .copied();
+        .max_by(|x, y| x.abs().total_cmp(&y.abs()))
+        .copied()
+        .or(snapshot.cpu_temp);
 
     appdata.cpu_maxtemp_buffer.add(max_temp.unwrap_or(0.0));
 
-    let cpu_power = ohw_opt.parse_value_path_def("#0|+images_icon/cpu.png|Power|Package");
+    let cpu_power = snapshot.cpu_power.unwrap_or_default();
 
-    let mut s = appdata.settings.lock();
-    if cpu_power > s.current_settings.max_cpu_power {
-        s.current_settings.max_cpu_power = cpu_power;
+    // without a known limit, learn it from the highest value seen
+    appdata.cpu_power_limit = snapshot.cpu_power_limit;
+    if appdata.cpu_power_limit.is_none() {
+        let mut s = appdata.settings.lock();
+        if cpu_power > s.current_settings.max_cpu_power {
+            s.current_settings.max_cpu_power = cpu_power;
+        }
+        drop(s);
     }
-    drop(s);
     appdata.cpu_power_buffer.add(cpu_power);
 }
 
-pub fn refresh_battery(appdata: &mut MyApp) {
-    let level: f64 = appdata
-        .ohw_info
-        .lock()
-        .parse_value_path_def("#0|+images_icon/battery.png|levels|charge");
+/// One GPU with its history. GPUs are matched by PCI bus id between refreshes, or by position
+/// when there is none.
+pub struct GpuState {
+    pub data: GpuData,
+    pub buffer: Arc<CircleVec<f64, 100>>,
+    pub mem_buffer: Arc<CircleVec<f64, 100>>,
+    pub power_buffer: Arc<CircleVec<f64, 100>>,
+    pub temp_buffer: Arc<CircleVec<f64, 100>>,
+}
+
+impl GpuState {
+    fn new(data: GpuData) -> Self {
+        Self {
+            data,
+            buffer: CircleVec::new(),
+            mem_buffer: CircleVec::new(),
+            power_buffer: CircleVec::new(),
+            temp_buffer: CircleVec::new(),
+        }
+    }
+}
+
+fn refresh_gpu(appdata: &mut MyApp, snapshot: &mut MetricSnapshot) {
+    let Some(gpus) = snapshot.gpus.take() else {
+        return;
+    };
+    // GPUs that disappeared keep their last values, devices don't come and go at runtime
+    for (i, g) in gpus.into_iter().enumerate() {
+        // the pipeline keeps its order, a GPU without a bus id is the one at the same position
+        let existing = (!g.bus_id.is_empty())
+            .then(|| appdata.gpus.iter().position(|s| s.data.bus_id == g.bus_id))
+            .flatten()
+            .or_else(|| {
+                Some(i).filter(|i| {
+                    appdata
+                        .gpus
+                        .get(*i)
+                        .is_some_and(|s| s.data.bus_id.is_empty() || g.bus_id.is_empty())
+                })
+            });
+        let state = match existing {
+            Some(idx) => &mut appdata.gpus[idx],
+            None => {
+                appdata.gpus.push(GpuState::new(Default::default()));
+                appdata.gpus.last_mut().unwrap()
+            }
+        };
+
+        state.buffer.add(g.utilization);
+        state
+            .mem_buffer
+            .add((g.memory_used / g.memory_total.max(0.01)) as f64);
+        state
+            .power_buffer
+            .add((g.power_usage / g.power_limit.max(0.01)) as f64);
+        state.temp_buffer.add((g.temperature) as f64);
+
+        state.data = g;
+    }
+}
+
+pub fn refresh_battery(appdata: &mut MyApp, snapshot: &mut MetricSnapshot) {
+    let Some(battery) = snapshot.battery.take() else {
+        return;
+    };
 
-    if level != 0.0 {
+    if battery.level != 0.0 {
         appdata.battery_enabled = true;
-        let ohw = appdata.ohw_info.lock();
-        let mut charge =
-            -ohw.parse_value_path_def::<f64>("#0|+images_icon/battery.png|currents|discharge");
-        if charge == -0.0 {
-            charge = ohw.parse_value_path_def("#0|+images_icon/battery.png|currents|charge");
-        }
-        drop(ohw);
-        appdata.battery_change_buffer.add(charge);
+        appdata.battery_change_buffer.add(battery.charge_rate);
+        appdata.battery = Some(battery.clone());
 
         let now = Local::now().naive_local();
         if now > appdata.battery_level_next_update {
-            appdata.battery_level_buffer.add(level);
+            appdata.battery_level_buffer.add(battery.level);
             appdata.battery_level_next_update =
                 now + chrono::Duration::seconds(60 - now.time().second() as i64);
         }
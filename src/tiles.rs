@@ -0,0 +1,293 @@
+use std::sync::Arc;
+
+use eframe::{
+    egui::{
+        plot::{Line, PlotPoints},
+        RichText, Ui,
+    },
+    epaint::Color32,
+};
+
+use crate::{
+    bytes_format::format_bytes,
+    circlevec::CircleVec,
+    color::auto_color_dark,
+    components::edgy_progress::EdgyProgressBar,
+    ohw::{MyNode, Selector},
+    quantity::{Quantity, Unit},
+    settings::{TileSettings, TileStyle},
+    sources::MetricSnapshot,
+    step_timing,
+    system_info::add_graph,
+    CurrentStep, MyApp,
+};
+
+/// Live value and history of one custom tile.
+pub struct TileState {
+    /// The settings this state belongs to, the history starts over when they change.
+    settings: TileSettings,
+    /// The parsed source, unless it is a `metric:`
+    selector: Option<Selector>,
+    /// In the tile's unit
+    value: Option<f64>,
+    text: String,
+    error: Option<String>,
+    buffer: Arc<CircleVec<f64, 100>>,
+}
+
+impl TileState {
+    fn new(settings: TileSettings) -> Self {
+        Self {
+            selector: (!settings.source.starts_with("metric:"))
+                .then(|| Selector::parse(&settings.source)),
+            settings,
+            value: None,
+            text: String::new(),
+            error: None,
+            buffer: CircleVec::new(),
+        }
+    }
+}
+
+pub fn refresh_tiles(appdata: &mut MyApp, snapshot: &MetricSnapshot) {
+    let tiles = appdata.settings.lock().current_settings.tiles.clone();
+    appdata.tiles.truncate(tiles.len());
+
+    let ohw = appdata.ohw_info.lock();
+    for (i, settings) in tiles.into_iter().enumerate() {
+        if appdata
+            .tiles
+            .get(i)
+            .map(|t| t.settings != settings)
+            .unwrap_or(true)
+        {
+            let state = TileState::new(settings);
+            if i < appdata.tiles.len() {
+                appdata.tiles[i] = state;
+            } else {
+                appdata.tiles.push(state);
+            }
+        }
+
+        let reading = read_tile(&appdata.tiles[i], &*ohw, snapshot);
+        let state = &mut appdata.tiles[i];
+        match reading {
+            Ok((value, text)) => {
+                state.buffer.add(value);
+                state.value = Some(value);
+                state.text = text;
+                state.error = None;
+            }
+            Err(e) => {
+                state.buffer.add(0.0);
+                state.value = None;
+                state.text = "n/a".to_string();
+                state.error = Some(e);
+            }
+        }
+    }
+}
+
+/// The value in the tile's unit and its display text.
+fn read_tile(
+    state: &TileState,
+    ohw: &impl MyNode,
+    snapshot: &MetricSnapshot,
+) -> Result<(f64, String), String> {
+    let tile = &state.settings;
+    let q = match &state.selector {
+        Some(selector) => ohw.quantity_parsed(selector).map_err(|e| e.to_string())?,
+        None => {
+            let name = tile.source.trim_start_matches("metric:");
+            snapshot
+                .metric(name)
+                .ok_or_else(|| format!("{name}: no such metric"))?
+        }
+    };
+    if tile.unit.is_empty() {
+        return Ok((q.value, format_quantity(q)));
+    }
+    let value = q
+        .value_in(&tile.unit)
+        .ok_or_else(|| format!("can't show {} in {}", q.unit.symbol(), tile.unit))?;
+    Ok((value, format!("{} {}", format_number(value), tile.unit)))
+}
+
+fn format_quantity(q: Quantity) -> String {
+    match q.unit {
+        Unit::Byte => format_bytes(q.value),
+        Unit::BytePerSecond => format!("{}/s", format_bytes(q.value)),
+        Unit::None => format_number(q.value),
+        _ => format!("{} {}", format_number(q.value), q.unit.symbol()),
+    }
+}
+
+fn format_number(v: f64) -> String {
+    if v.abs() >= 100.0 {
+        format!("{v:.0}")
+    } else {
+        format!("{v:.1}")
+    }
+}
+
+pub fn show_tiles(appdata: &mut MyApp, ui: &mut Ui) {
+    if appdata.tiles.is_empty() {
+        return;
+    }
+    ui.vertical_centered(|ui| ui.label("Sensors"));
+    for (i, tile) in appdata.tiles.iter().enumerate() {
+        let s = &tile.settings;
+        let color = s
+            .color
+            .map(|[r, g, b]| Color32::from_rgb(r, g, b))
+            .unwrap_or_else(|| auto_color_dark((i % 10) as i32));
+        let text = RichText::new(format!("{}: {}", s.label, tile.text))
+            .small()
+            .strong();
+
+        let response = match s.style {
+            TileStyle::Bar => {
+                let fraction =
+                    (tile.value.unwrap_or(s.min) - s.min) / (s.max - s.min).max(f64::EPSILON);
+                ui.add(EdgyProgressBar::new(fraction as f32).text(text).fill(color))
+            }
+            TileStyle::Graph | TileStyle::Text => ui.label(text),
+        };
+        if let Some(e) = &tile.error {
+            response.on_hover_text(e);
+        }
+
+        if s.style == TileStyle::Graph {
+            let values = tile.buffer.read();
+            let line = Line::new(
+                (0..tile.buffer.capacity())
+                    .map(|i| [i as f64, { values[i] }])
+                    .collect::<PlotPoints>(),
+            )
+            .color(color);
+            add_graph(&format!("tile {i}"), ui, vec![line], &[s.min, s.max]);
+        }
+    }
+    ui.separator();
+    step_timing(appdata, CurrentStep::Tiles);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ohw::OHWNode;
+
+    use super::*;
+
+    fn node(text: &str, value: &str, children: Vec<OHWNode>) -> OHWNode {
+        OHWNode {
+            Text: text.to_string(),
+            Value: value.to_string(),
+            Children: children,
+            ..Default::default()
+        }
+    }
+
+    fn tree() -> OHWNode {
+        node(
+            "Sensor",
+            "",
+            vec![node(
+                "PC",
+                "",
+                vec![
+                    node("GPU Core", "1905,0 MHz", vec![]),
+                    node("GPU Memory", "2,5 GB", vec![]),
+                    node("Fan #1", "1200 RPM", vec![]),
+                    node("Fan #2", "1.2.3 RPM", vec![]),
+                ],
+            )],
+        )
+    }
+
+    fn snapshot() -> MetricSnapshot {
+        MetricSnapshot {
+            cpu_power: Some(42.5),
+            ram_used: Some(8.0 * 1024.0 * 1024.0 * 1024.0),
+            ..Default::default()
+        }
+    }
+
+    fn read(source: &str, unit: &str) -> Result<(f64, String), String> {
+        let state = TileState::new(TileSettings {
+            source: source.to_string(),
+            unit: unit.to_string(),
+            ..Default::default()
+        });
+        read_tile(&state, &tree(), &snapshot())
+    }
+
+    #[test]
+    fn converts_to_the_display_unit() {
+        assert_eq!(
+            read("PC|GPU Core", "GHz"),
+            Ok((1.905, "1.9 GHz".to_string()))
+        );
+        assert_eq!(
+            read("PC|GPU Core", "MHz"),
+            Ok((1905.0, "1905 MHz".to_string()))
+        );
+        assert_eq!(
+            read("PC|GPU Memory", "MB"),
+            Ok((2560.0, "2560 MB".to_string()))
+        );
+    }
+
+    #[test]
+    fn base_unit_without_display_unit() {
+        assert_eq!(read("PC|Fan #1", ""), Ok((1200.0, "1200 RPM".to_string())));
+        assert_eq!(
+            read("PC|GPU Memory", ""),
+            Ok((2.5 * 1024.0 * 1024.0 * 1024.0, "2.5 GB".to_string()))
+        );
+    }
+
+    #[test]
+    fn metrics_come_from_the_snapshot() {
+        assert_eq!(
+            read("metric:cpu_power", "W"),
+            Ok((42.5, "42.5 W".to_string()))
+        );
+        assert_eq!(
+            read("metric:ram_used", "GB"),
+            Ok((8.0, "8.0 GB".to_string()))
+        );
+        // in the snapshot's type, but not provided
+        assert_eq!(
+            read("metric:cpu_temp", "°C"),
+            Err("cpu_temp: no such metric".to_string())
+        );
+        assert_eq!(
+            read("metric:gpu_magic", ""),
+            Err("gpu_magic: no such metric".to_string())
+        );
+    }
+
+    #[test]
+    fn missing_or_unreadable_sensor() {
+        assert_eq!(
+            read("PC|Pump", "RPM"),
+            Err("PC|Pump: no such sensor".to_string())
+        );
+        assert_eq!(
+            read("PC|Fan #2", "RPM"),
+            Err("PC|Fan #2: '1.2.3' is not a number".to_string())
+        );
+    }
+
+    #[test]
+    fn wrong_display_unit() {
+        assert_eq!(
+            read("PC|Fan #1", "°C"),
+            Err("can't show RPM in °C".to_string())
+        );
+        assert_eq!(
+            read("metric:cpu_power", "MB"),
+            Err("can't show W in MB".to_string())
+        );
+    }
+}